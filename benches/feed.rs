@@ -0,0 +1,270 @@
+//! Timing of feed queries against a seeded database of 100k articles.
+//!
+//! Run with `cargo bench --features ssr --bench feed`. The database is in
+//! memory, so the configured one is not touched.
+//!
+//! The queries for the details of the articles on a page (tags and favorites)
+//! are also timed the way they used to be done, over the whole tables, and
+//! limited to the page but without the indexes by article.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use demo_app::models::article::{cursor_after, Feed, FeedOptions};
+use sqlx::QueryBuilder;
+
+const USERS: usize = 1_000;
+const ARTICLES: usize = 100_000;
+const TAGS: &[&str] = &[
+    "rust", "wasm", "web", "sqlite", "leptos", "axum", "async", "testing", "design", "css",
+];
+const ITERATIONS: u32 = 20;
+
+/// Small deterministic generator, so every run gets the same data
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize
+    }
+}
+
+async fn seed() -> Result<(), sqlx::Error> {
+    let mut rng = Lcg(42);
+    let mut tx = demo_app::db::get().begin().await?;
+
+    let users: Vec<_> = (0..USERS).collect();
+    for chunk in users.chunks(1000) {
+        QueryBuilder::new("insert into user (username, email, password) ")
+            .push_values(chunk, |mut row, i| {
+                row.push_bind(format!("user{}", i))
+                    .push_bind(format!("user{}@example.com", i))
+                    .push_bind("not a hash");
+            })
+            .build()
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let now = chrono::Utc::now();
+    let articles: Vec<_> = (0..ARTICLES).collect();
+    for chunk in articles.chunks(1000) {
+        let authors: Vec<_> = chunk.iter().map(|_| rng.next() % USERS).collect();
+        QueryBuilder::new(
+            "insert into article (slug, title, description, body, created_at, author) ",
+        )
+        .push_values(chunk.iter().zip(authors), |mut row, (i, author)| {
+            let created_at = now - chrono::TimeDelta::minutes(*i as i64);
+            row.push_bind(format!("article-{}", i))
+                .push_bind(format!("Article {}", i))
+                .push_bind("Seeded article")
+                .push_bind("Lorem ipsum dolor sit amet.")
+                .push_bind(created_at.format("%Y-%m-%d %H:%M:%S").to_string())
+                .push_bind(format!("user{}", author));
+        })
+        .build()
+        .execute(&mut *tx)
+        .await?;
+
+        let mut tags = Vec::new();
+        let mut favorites = Vec::new();
+        for i in chunk {
+            for _ in 0..3 {
+                tags.push((*i, TAGS[rng.next() % TAGS.len()]));
+            }
+            for _ in 0..rng.next() % 5 {
+                favorites.push((*i, rng.next() % USERS));
+            }
+        }
+        QueryBuilder::new("insert or ignore into tag (article, tag) ")
+            .push_values(tags, |mut row, (i, tag)| {
+                row.push_bind(format!("article-{}", i)).push_bind(tag);
+            })
+            .build()
+            .execute(&mut *tx)
+            .await?;
+        if !favorites.is_empty() {
+            QueryBuilder::new("insert or ignore into favorite (article, user) ")
+                .push_values(favorites, |mut row, (i, user)| {
+                    row.push_bind(format!("article-{}", i))
+                        .push_bind(format!("user{}", user));
+                })
+                .build()
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    // The reader of the feeds follows some authors
+    QueryBuilder::new("insert into follow (follower, followed) ")
+        .push_values(1..=50, |mut row, i| {
+            row.push_bind("user0").push_bind(format!("user{}", i * 7));
+        })
+        .build()
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+/// Average time of the query, which gives the number of rows it got
+async fn time<F, Fut>(name: &str, f: F) -> Duration
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<usize, sqlx::Error>>,
+{
+    // Warm up caches
+    f().await.expect("query");
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let rows = f().await.expect("query");
+        total += start.elapsed();
+        assert!(rows != 0, "{} should not be empty", name);
+    }
+    total / ITERATIONS
+}
+
+async fn measure<F, Fut>(name: &str, f: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<Feed, sqlx::Error>>,
+{
+    let average = time(name, || {
+        let feed = f();
+        async move { feed.await.map(|feed| feed.articles.len()) }
+    })
+    .await;
+    println!("{:<24} {:>10.2?} / page", name, average);
+}
+
+/// Tags and favorites of all the articles, as before the details were
+/// limited to the page
+async fn details_of_all() -> Result<usize, sqlx::Error> {
+    let db = demo_app::db::get();
+    let tags = sqlx::query("select tag, article from tag")
+        .fetch_all(db)
+        .await?;
+    let favorites = sqlx::query("select article, count(*) as count from favorite group by article")
+        .fetch_all(db)
+        .await?;
+    let favorited = sqlx::query("select article from favorite where user = ?")
+        .bind("user0")
+        .fetch_all(db)
+        .await?;
+    Ok(tags.len() + favorites.len() + favorited.len())
+}
+
+/// Tags and favorites of the articles on the page, as the feeds get them
+async fn details_of_page(slugs: &str) -> Result<usize, sqlx::Error> {
+    let db = demo_app::db::get();
+    let tags = sqlx::query(
+        "select tag, article from tag where article in (select value from json_each(?))",
+    )
+    .bind(slugs)
+    .fetch_all(db)
+    .await?;
+    let favorites = sqlx::query(
+        "
+        select article, count(*) as count from favorite
+        where article in (select value from json_each(?))
+        group by article
+        ",
+    )
+    .bind(slugs)
+    .fetch_all(db)
+    .await?;
+    let favorited = sqlx::query(
+        "
+        select article from favorite
+        where user = ? and article in (select value from json_each(?))
+        ",
+    )
+    .bind("user0")
+    .bind(slugs)
+    .fetch_all(db)
+    .await?;
+    Ok(tags.len() + favorites.len() + favorited.len())
+}
+
+async fn compare_details() {
+    let db = demo_app::db::get();
+    let slugs: Vec<String> =
+        sqlx::query_scalar("select slug from article order by created_at desc limit 10")
+            .fetch_all(db)
+            .await
+            .expect("first page");
+    let slugs = serde_json::to_string(&slugs).expect("slugs as json");
+    let slugs = slugs.as_str();
+
+    let all = time("details of all", details_of_all).await;
+    for index in ["tag_article", "favorite_article"] {
+        sqlx::query(&format!("drop index {}", index))
+            .execute(db)
+            .await
+            .expect("dropped index");
+    }
+    let unindexed = time("details of page", || details_of_page(slugs)).await;
+    for index in [
+        "create index tag_article on tag(article)",
+        "create index favorite_article on favorite(article)",
+    ] {
+        sqlx::query(index)
+            .execute(db)
+            .await
+            .expect("recreated index");
+    }
+    let indexed = time("details of page", || details_of_page(slugs)).await;
+
+    println!("details of a page:");
+    println!("  {:<22} {:>10.2?}", "whole tables (before)", all);
+    println!("  {:<22} {:>10.2?}", "page, no index", unindexed);
+    println!(
+        "  {:<22} {:>10.2?} ({:.0}x faster)",
+        "page, indexed (after)",
+        indexed,
+        all.as_secs_f64() / indexed.as_secs_f64()
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    demo_app::db::init_with("sqlite::memory:").await;
+
+    let start = Instant::now();
+    seed().await.expect("seeded database");
+    println!("seeded {} articles in {:.2?}", ARTICLES, start.elapsed());
+
+    let middle: String = sqlx::query_scalar("select created_at from article where slug = ?")
+        .bind("article-50000")
+        .fetch_one(demo_app::db::get())
+        .await
+        .expect("middle article");
+
+    let options = || FeedOptions {
+        limit: 10,
+        user: Some("user0".into()),
+        ..Default::default()
+    };
+    let deep = || FeedOptions {
+        cursor: Some(cursor_after(&middle, "article-50000")),
+        ..options()
+    };
+
+    measure("global", || async move { Feed::global(&options()).await }).await;
+    measure("global, deep page", || async move {
+        Feed::global(&deep()).await
+    })
+    .await;
+    measure("tag", || async move { Feed::tag("rust", &options()).await }).await;
+    measure("followed authors", || async move {
+        Feed::feed("user0", &options()).await
+    })
+    .await;
+
+    compare_details().await;
+}
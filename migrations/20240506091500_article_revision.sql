@@ -0,0 +1,7 @@
+/*
+Revision counter for optimistic concurrency control of article edits. The
+editor submits the revision it was loaded from, and the update only applies
+if it still matches the stored one.
+*/
+
+alter table article add column revision integer not null default 0;
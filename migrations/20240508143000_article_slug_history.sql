@@ -0,0 +1,12 @@
+/*
+Previous slugs of renamed articles, so old links can be redirected to the
+current slug. Rows follow the article through further renames thanks to the
+cascading foreign key.
+*/
+
+create table if not exists article_slug_history (
+	slug text not null primary key,
+	changed_at text not null default (datetime('now')),
+
+	article text not null references article(slug) on delete cascade on update cascade
+);
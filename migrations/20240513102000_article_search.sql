@@ -0,0 +1,79 @@
+/*
+Full-text search over articles. The index keeps its own copy of the
+searchable text, kept in sync with the triggers below. Tags are indexed as a
+space separated list.
+
+The rows of the index are keyed by `article_search_key`, so that they can be
+found without scanning the unindexed slug column. The rowids of articles
+can't be used for that, as they aren't stable (e.g. VACUUM can renumber them).
+*/
+
+create table if not exists article_search_key (
+	id integer primary key,
+	article text not null unique
+);
+
+create virtual table if not exists article_fts using fts5(
+	slug unindexed,
+	title,
+	description,
+	body,
+	tags,
+	tokenize = 'porter unicode61 remove_diacritics 2'
+);
+
+insert into article_search_key (article) select slug from article;
+
+insert into article_fts (rowid, slug, title, description, body, tags)
+select
+	article_search_key.id, slug, title, description, body,
+	coalesce((select group_concat(tag, ' ') from tag where tag.article = article.slug), '')
+from article
+join article_search_key on article_search_key.article = article.slug;
+
+create trigger if not exists article_fts_insert after insert on article begin
+	insert into article_search_key (article) values (new.slug);
+	insert into article_fts (rowid, slug, title, description, body, tags)
+	values (
+		(select id from article_search_key where article = new.slug),
+		new.slug, new.title, new.description, new.body, ''
+	);
+end;
+
+create trigger if not exists article_fts_update
+after update of slug, title, description, body on article begin
+	update article_search_key set article = new.slug where article = old.slug;
+	update article_fts set
+		slug = new.slug,
+		title = new.title,
+		description = new.description,
+		body = new.body
+	where rowid = (select id from article_search_key where article = new.slug);
+end;
+
+create trigger if not exists article_fts_delete after delete on article begin
+	delete from article_fts
+	where rowid = (select id from article_search_key where article = old.slug);
+	delete from article_search_key where article = old.slug;
+end;
+
+create trigger if not exists article_fts_tag_insert after insert on tag begin
+	update article_fts
+	set tags = (select group_concat(tag, ' ') from tag where article = new.article)
+	where rowid = (select id from article_search_key where article = new.article);
+end;
+
+create trigger if not exists article_fts_tag_delete after delete on tag begin
+	update article_fts
+	set tags = coalesce((select group_concat(tag, ' ') from tag where article = old.article), '')
+	where rowid = (select id from article_search_key where article = old.article);
+end;
+
+create trigger if not exists article_fts_tag_update after update of tag on tag begin
+	update article_fts
+	set tags = coalesce((select group_concat(tag, ' ') from tag where article = old.article), '')
+	where rowid = (select id from article_search_key where article = old.article);
+	update article_fts
+	set tags = (select group_concat(tag, ' ') from tag where article = new.article)
+	where rowid = (select id from article_search_key where article = new.article);
+end;
@@ -0,0 +1,8 @@
+/*
+The details of the articles on a feed page look up their tags and favorites
+by article, which the primary keys, starting with the tag and the user, don't
+cover.
+*/
+
+create index if not exists tag_article on tag(article);
+create index if not exists favorite_article on favorite(article);
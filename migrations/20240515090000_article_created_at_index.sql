@@ -0,0 +1,6 @@
+/*
+Feeds are ordered by the creation time of the articles, and paged by their
+position in that order.
+*/
+
+create index if not exists article_created_at on article(created_at, slug);
@@ -0,0 +1,22 @@
+/*
+Secret tokens for the private "Your Feed" syndication URLs. Resetting the
+token revokes the previous URL.
+
+Articles also get a random id for the ids of their feed entries, which must
+not change even when the slug does, so feed readers don't show them again.
+*/
+
+create table if not exists feed_token (
+	user text not null primary key references user(username) on delete cascade on update cascade,
+	token text not null unique,
+	created_at text not null default (datetime('now'))
+);
+
+alter table article add column entry_id text null;
+
+update article set entry_id = lower(hex(randomblob(16)));
+
+create trigger if not exists article_entry_id after insert on article
+when new.entry_id is null begin
+	update article set entry_id = lower(hex(randomblob(16))) where slug = new.slug;
+end;
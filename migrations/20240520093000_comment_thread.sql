@@ -0,0 +1,9 @@
+/*
+Threaded comments. Replies refer to their parent comment, and a deleted
+comment with replies is kept as a placeholder so the replies stay in place.
+*/
+
+alter table comment add column parent_id integer null references comment(id) on delete cascade on update cascade;
+alter table comment add column deleted boolean not null default false;
+
+create index if not exists comment_parent on comment(parent_id);
@@ -0,0 +1,19 @@
+/*
+Editing of comments. The previous versions of an edited comment are kept for
+moderators, with the time each was written.
+*/
+
+alter table user add column moderator boolean not null default false;
+
+alter table comment add column edited_at text null;
+
+create table if not exists comment_revision (
+	id integer primary key,
+	body text not null,
+	written_at text not null,
+	replaced_at text not null default (datetime('now')),
+
+	comment integer not null references comment(id) on delete cascade on update cascade
+);
+
+create index if not exists comment_revision_comment on comment_revision(comment);
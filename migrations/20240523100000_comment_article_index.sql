@@ -0,0 +1,5 @@
+/*
+Comments are listed and counted by article.
+*/
+
+create index if not exists comment_article on comment(article);
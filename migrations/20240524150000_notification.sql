@@ -0,0 +1,29 @@
+/*
+Notifications about the activity of others concerning the user, e.g. new
+followers. Repeated follows and favorites by the same user update the
+existing notification instead of adding more.
+*/
+
+create table if not exists notification (
+	id integer primary key,
+	kind text not null,
+	created_at text not null default (datetime('now')),
+	read_at text null,
+
+	user text not null references user(username) on delete cascade on update cascade,
+	actor text not null references user(username) on delete cascade on update cascade,
+	article text null references article(slug) on delete cascade on update cascade,
+	comment integer null references comment(id) on delete cascade on update cascade
+);
+
+create index if not exists notification_user on notification(user, created_at);
+
+/*
+Kinds of notifications the user has turned off
+*/
+
+create table if not exists notification_opt_out (
+	user text not null references user(username) on delete cascade on update cascade,
+	kind text not null,
+	primary key (user, kind)
+);
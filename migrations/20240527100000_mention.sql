@@ -0,0 +1,16 @@
+/*
+Users mentioned with `@username` in articles and comments. Kept so that
+editing only notifies the users who weren't mentioned before.
+*/
+
+create table if not exists article_mention (
+	article text not null references article(slug) on delete cascade on update cascade,
+	user text not null references user(username) on delete cascade on update cascade,
+	primary key (article, user)
+);
+
+create table if not exists comment_mention (
+	comment integer not null references comment(id) on delete cascade on update cascade,
+	user text not null references user(username) on delete cascade on update cascade,
+	primary key (comment, user)
+);
@@ -0,0 +1,11 @@
+/*
+Email digests of new articles and notifications. The token is for the
+unsubscribe links in the emails, which work without logging in.
+*/
+
+create table if not exists digest (
+	user text not null primary key references user(username) on delete cascade on update cascade,
+	frequency text not null default 'never',
+	token text not null unique,
+	sent_at text null
+);
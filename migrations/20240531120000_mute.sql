@@ -0,0 +1,13 @@
+/*
+Users whose content the user doesn't want to see. Blocking also keeps the
+muted user from following, commenting on the articles of, or mentioning
+the user.
+*/
+
+create table if not exists mute (
+	user text not null references user(username) on delete cascade on update cascade,
+	muted text not null references user(username) on delete cascade on update cascade,
+	block boolean not null default false,
+	created_at text not null default (datetime('now')),
+	primary key (user, muted)
+);
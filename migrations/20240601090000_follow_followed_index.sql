@@ -0,0 +1,6 @@
+/*
+Followers of a user are counted and listed by the followed user, which the
+primary key, starting with the follower, doesn't cover.
+*/
+
+create index if not exists follow_followed on follow(followed);
@@ -0,0 +1,12 @@
+/*
+Private "save for later" bookmarks of articles, separate from the public
+favorites. Read when the user has opened the article since saving it.
+*/
+
+create table if not exists bookmark (
+	user text not null references user(username) on delete cascade on update cascade,
+	article text not null references article(slug) on delete cascade on update cascade,
+	created_at text not null default (datetime('now')),
+	read_at text null,
+	primary key (user, article)
+);
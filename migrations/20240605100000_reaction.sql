@@ -0,0 +1,35 @@
+/*
+Emoji reactions to articles and comments, from a fixed set. A user can give
+each of the reactions once.
+
+The reactions to an article are also counted on it, kept up to date by the
+triggers below, so feeds can be ordered by them along an index.
+*/
+
+create table if not exists article_reaction (
+	article text not null references article(slug) on delete cascade on update cascade,
+	user text not null references user(username) on delete cascade on update cascade,
+	reaction text not null,
+	created_at text not null default (datetime('now')),
+	primary key (article, user, reaction)
+);
+
+create table if not exists comment_reaction (
+	comment integer not null references comment(id) on delete cascade on update cascade,
+	user text not null references user(username) on delete cascade on update cascade,
+	reaction text not null,
+	created_at text not null default (datetime('now')),
+	primary key (comment, user, reaction)
+);
+
+alter table article add column reaction_count integer not null default 0;
+
+create index if not exists article_reaction_count on article(reaction_count, created_at, slug);
+
+create trigger if not exists article_reaction_count_insert after insert on article_reaction begin
+	update article set reaction_count = reaction_count + 1 where slug = new.article;
+end;
+
+create trigger if not exists article_reaction_count_delete after delete on article_reaction begin
+	update article set reaction_count = reaction_count - 1 where slug = old.article;
+end;
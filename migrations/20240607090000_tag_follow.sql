@@ -0,0 +1,11 @@
+/*
+Tags followed by the users. Articles with them are in the personal feed along
+with the articles of the followed users.
+*/
+
+create table if not exists tag_follow (
+	user text not null references user(username) on delete cascade on update cascade,
+	tag text not null,
+	created_at text not null default (datetime('now')),
+	primary key (user, tag)
+);
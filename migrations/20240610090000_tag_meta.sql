@@ -0,0 +1,14 @@
+/*
+Descriptions of tags, and aliases that are replaced with their tag when
+articles are saved, so that e.g. `js` and `javascript` don't end up as
+separate tags. Aliases are never aliases of other aliases.
+*/
+
+create table if not exists tag_meta (
+	tag text primary key not null,
+	description text not null default '',
+	alias_of text null,
+	updated_at text not null default (datetime('now'))
+);
+
+create index if not exists tag_meta_alias_of on tag_meta (alias_of);
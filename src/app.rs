@@ -10,12 +10,14 @@ use crate::{
         article::Article,
         editor,
         feed::{Feed, FeedKind},
+        notifications::{MarkNotificationsRead, NotificationBell, Notifications},
         profile::{profile_link, ProfileImg, ProfileRoute},
+        tag::{TagFollowButton, TagInfo},
         user::{Login, Register, Settings},
     },
 };
 use leptos::*;
-use leptos_meta::{provide_meta_context, Stylesheet, Title};
+use leptos_meta::{provide_meta_context, Link, Stylesheet, Title};
 use leptos_router::*;
 
 pub(crate) fn use_current_user() -> Signal<Option<User>> {
@@ -38,6 +40,8 @@ pub fn App() -> impl IntoView {
     );
     let maybe_user = Signal::derive(move || user().and_then(Result::ok).flatten());
     provide_context(maybe_user);
+    // Shared by the notification bell and page
+    provide_context(create_server_action::<MarkNotificationsRead>());
 
     view! {
         <Title text="Conduit"/>
@@ -93,7 +97,13 @@ pub fn App() -> impl IntoView {
                             path=""
                             view=move || {
                                 view! {
-                                    <Feed kind=FeedKind::Global>
+                                    <Link
+                                        rel="alternate"
+                                        type_="application/atom+xml"
+                                        title="Conduit"
+                                        href="/feed.atom"
+                                    />
+                                    <Feed kind=FeedKind::Global infinite=true>
                                         <UserFeedLink href="/feed"/>
                                         <NavLink href="">Global Feed</NavLink>
                                     </Feed>
@@ -113,6 +123,36 @@ pub fn App() -> impl IntoView {
                             }
                         />
 
+                        <Route
+                            path="/bookmarks"
+                            view=move || {
+                                view! {
+                                    <Feed kind=FeedKind::Bookmarks>
+                                        <UserFeedLink href="/feed"/>
+                                        <NavLink href="/">Global Feed</NavLink>
+                                        <NavLink href="">Reading list</NavLink>
+                                    </Feed>
+                                }
+                            }
+                        />
+
+                        <Route
+                            path="/search"
+                            view=move || {
+                                let query = use_query_map();
+                                let search = move || {
+                                    query.with(|map| map.get("q").cloned().unwrap_or_default())
+                                };
+                                view! {
+                                    <Feed kind=Signal::derive(move || FeedKind::Search(search()))>
+                                        <UserFeedLink href="/feed"/>
+                                        <NavLink href="/">Global Feed</NavLink>
+                                        <NavLink href="">"Search: " {search}</NavLink>
+                                    </Feed>
+                                }
+                            }
+                        />
+
                         <Route
                             path="/tag/:tag"
                             view=move || {
@@ -121,10 +161,24 @@ pub fn App() -> impl IntoView {
                                     params.with(|map| map.get("tag").cloned().unwrap_or_default())
                                 };
                                 view! {
-                                    <Feed kind=Signal::derive(move || FeedKind::Tag(tag()))>
+                                    {move || {
+                                        view! {
+                                            <Link
+                                                rel="alternate"
+                                                type_="application/atom+xml"
+                                                title=format!("#{} — Conduit", tag())
+                                                href=format!("/tag/{}/feed.atom", tag())
+                                            />
+                                        }
+                                    }}
+                                    <Feed
+                                        kind=Signal::derive(move || FeedKind::Tag(tag()))
+                                        header=view! { <TagInfo tag=Signal::derive(tag)/> }.into_view()
+                                    >
                                         <UserFeedLink href="/feed"/>
                                         <NavLink href="/">Global Feed</NavLink>
                                         <NavLink href=""># {tag}</NavLink>
+                                        <TagFollowButton tag=Signal::derive(tag)/>
                                     </Feed>
                                 }
                             }
@@ -134,6 +188,7 @@ pub fn App() -> impl IntoView {
                     <Route path="/login" view=move || view! { <Login login=login/> }/>
                     <Route path="/register" view=move || view! { <Register register=register/> }/>
                     <Route path="/settings" view=move || view! { <Settings logout=logout/> }/>
+                    <Route path="/notifications" view=Notifications/>
                     <ProfileRoute/>
                     <Route path="/article/:slug" view=Article/>
                     <Route path="/editor" view=editor::New/>
@@ -181,6 +236,12 @@ fn Nav(logout: crate::auth::LogoutAction) -> impl IntoView {
                     {NBSP}
                     New Article
                 </NavLink>
+                <NavLink href="/bookmarks">
+                    <i class="ion-bookmark"></i>
+                    {NBSP}
+                    Reading list
+                </NavLink>
+                <NotificationBell/>
                 <NavLink href="/settings">
                     <i class="ion-gear-a"></i>
                     {NBSP}
@@ -213,6 +274,16 @@ fn Nav(logout: crate::auth::LogoutAction) -> impl IntoView {
                     conduit
                 </A>
                 <ul class="nav navbar-nav pull-xs-right">
+                    <li class="nav-item">
+                        <Form action="/search" class="form-inline">
+                            <input
+                                type="search"
+                                name="q"
+                                class="form-control form-control-sm"
+                                placeholder="Search articles"
+                            />
+                        </Form>
+                    </li>
                     <NavLink href="/">Home</NavLink>
                     <Suspense>{links}</Suspense>
                 </ul>
@@ -304,7 +375,16 @@ async fn toggle_follow(user: String, current: bool) -> Result<bool, ServerFnErro
         // Can't follow oneself
         return Ok(false);
     }
-    if current {
+    let blocked = crate::models::mute::MutedUser::is_blocked(&user, &logged_in)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to check block: {:?}", e);
+            ServerFnError::ServerError("database error".into())
+        })?;
+    if blocked && !current {
+        return Err(ServerFnError::ServerError("Blocked by the user".into()));
+    }
+    let changed = if current {
         sqlx::query!(
             "delete from follow where follower = ? and followed = ?",
             logged_in,
@@ -323,7 +403,16 @@ async fn toggle_follow(user: String, current: bool) -> Result<bool, ServerFnErro
     .map_err(|e| {
         tracing::error!("failed to toggle follow: {:?}", e);
         ServerFnError::ServerError("database error".into())
-    })
+    })?;
+    if changed && !current {
+        use crate::models::notification::{Notification, NotificationKind};
+        let notified =
+            Notification::notify(&user, NotificationKind::Follow, &logged_in, None, None).await;
+        if let Err(e) = notified {
+            tracing::error!("failed to notify of follow: {:?}", e);
+        }
+    }
+    Ok(changed)
 }
 
 #[derive(Params, PartialEq, Eq, Clone)]
@@ -350,6 +439,12 @@ pub fn FollowButton<R: Fn() -> Profile + 'static + Copy, W: Fn(Profile) + 'stati
             // Note: bit awkward to work with slices
             let mut p = profile();
             p.following = !p.following;
+            // The viewer is now one of the followers, or no longer is
+            p.followers_count = if p.following {
+                p.followers_count + 1
+            } else {
+                p.followers_count.saturating_sub(1)
+            };
             set_profile(p);
         }
     });
@@ -101,6 +101,20 @@ pub fn require_login() -> Result<String, ServerFnError> {
     authenticated_username().ok_or_else(|| ServerFnError::ServerError("Not logged in".into()))
 }
 
+/// Username of the logged in user, if they are a moderator
+#[cfg(feature = "ssr")]
+pub async fn require_moderator() -> Result<String, ServerFnError> {
+    let username = require_login()?;
+    match User::get(&username).await {
+        Ok(user) if user.moderator => Ok(username),
+        Ok(_) => Err(ServerFnError::ServerError("Not a moderator".into())),
+        Err(e) => {
+            tracing::error!("failed to check moderator: {:?}", e);
+            Err(ServerFnError::ServerError("Could not find user".into()))
+        }
+    }
+}
+
 #[cfg(feature = "ssr")]
 pub fn authenticated_username() -> Option<String> {
     use_context::<http::request::Parts>().and_then(|req| server::get_username(&req.headers))
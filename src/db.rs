@@ -3,7 +3,11 @@ use std::sync::OnceLock;
 static POOL: OnceLock<sqlx::SqlitePool> = OnceLock::new();
 
 pub async fn init() {
-    let url = std::option_env!("DATABASE_URL").expect("database url");
+    init_with(std::option_env!("DATABASE_URL").expect("database url")).await;
+}
+
+/// Initialise with a database other than the configured one
+pub async fn init_with(url: &str) {
     // The sqlx::sqlite driver sets `PRAGMA foreign_keys = ON` by default
     let pool = sqlx::sqlite::SqlitePoolOptions::new()
         .max_connections(5)
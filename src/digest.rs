@@ -0,0 +1,403 @@
+//! Daily and weekly email digests of new articles from followed authors and
+//! unread notifications, and unsubscribing from them.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+
+use crate::{
+    mail::{Mailer, Message},
+    models::{
+        article::{Feed, FeedFilter, FeedOptions},
+        digest::{DigestFrequency, DigestSubscription},
+        notification::Notification,
+    },
+    pages::{notifications::describe, profile::profile_link},
+    syndication::escape,
+};
+
+const HTML_TEMPLATE: &str = include_str!("../templates/email/digest.html");
+const TEXT_TEMPLATE: &str = include_str!("../templates/email/digest.txt");
+
+/// How often due digests are looked for
+const CHECK_HOURS: u32 = 1;
+
+/// Replaces the `{{name}}` placeholders of the template. Values are not
+/// looked into, so user content can't add placeholders.
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len() * 2);
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end) else {
+            break;
+        };
+        let name = &rest[start + 2..end];
+        out.push_str(&rest[..start]);
+        match values.iter().find(|(n, _)| *n == name) {
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Digest contents: new articles (and how many there were in total), and
+/// the text and link of the notifications
+struct Digest {
+    feed: Feed,
+    notifications: Vec<(String, String)>,
+}
+
+impl Digest {
+    async fn collect(subscription: &DigestSubscription, days: u32) -> Result<Self, sqlx::Error> {
+        let options = FeedOptions {
+            user: Some(subscription.user.clone()),
+            filter: FeedFilter::default().within_days(days),
+            ..Default::default()
+        };
+        let feed = Feed::feed(&subscription.user, &options).await?;
+
+        let since = (chrono::Utc::now() - chrono::Duration::days(days.into()))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let notifications = Notification::for_user(&subscription.user)
+            .await?
+            .iter()
+            .filter(|n| !n.read && n.created_at >= since)
+            .map(describe)
+            .collect();
+        Ok(Self {
+            feed,
+            notifications,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.feed.articles.is_empty() && self.notifications.is_empty()
+    }
+
+    fn message(&self, base: &str, subscription: &DigestSubscription) -> Message {
+        let period = match subscription.frequency {
+            DigestFrequency::Weekly => "this week",
+            _ => "today",
+        };
+        let frequency = subscription.frequency.description().to_lowercase();
+        let subject = format!("Your {} Conduit digest", frequency);
+        let unsubscribe = format!("{}/unsubscribe/{}", base, subscription.token);
+        let settings = format!("{}/settings", base);
+
+        let more = (self.feed.count as usize).saturating_sub(self.feed.articles.len());
+        let mut articles_html = String::new();
+        let mut articles_text = String::new();
+        if !self.feed.articles.is_empty() {
+            articles_html.push_str("<h2>New articles</h2>\n<ul>\n");
+            articles_text.push_str("\nNew articles\n\n");
+            for article in &self.feed.articles {
+                let link = format!("{}/article/{}", base, article.slug);
+                let author = &article.author.username;
+                _ = writeln!(
+                    articles_html,
+                    r#"<li><a href="{}">{}</a> by <a href="{}">{}</a><br>{}</li>"#,
+                    escape(&link),
+                    escape(&article.title),
+                    escape(&format!("{}{}", base, profile_link(author))),
+                    escape(author),
+                    escape(&article.description),
+                );
+                _ = writeln!(
+                    articles_text,
+                    "* {} by {}\n  {}\n  {}",
+                    article.title, author, article.description, link
+                );
+            }
+            articles_html.push_str("</ul>\n");
+            if more > 0 {
+                _ = writeln!(
+                    articles_html,
+                    r#"<p><a href="{}/feed">{} more in your feed</a></p>"#,
+                    escape(base),
+                    more
+                );
+                _ = writeln!(articles_text, "\n{} more in your feed: {}/feed", more, base);
+            }
+        }
+
+        let mut notifications_html = String::new();
+        let mut notifications_text = String::new();
+        if !self.notifications.is_empty() {
+            notifications_html.push_str("<h2>Notifications</h2>\n<ul>\n");
+            notifications_text.push_str("\nNotifications\n\n");
+            for (text, link) in &self.notifications {
+                let link = format!("{}{}", base, link);
+                _ = writeln!(
+                    notifications_html,
+                    r#"<li><a href="{}">{}</a></li>"#,
+                    escape(&link),
+                    escape(text)
+                );
+                _ = writeln!(notifications_text, "* {}\n  {}", text, link);
+            }
+            notifications_html.push_str("</ul>\n");
+        }
+
+        let fill = |template: &str, articles: &str, notifications: &str, html: bool| {
+            let text = |s: &str| if html { escape(s) } else { s.to_owned() };
+            render(
+                template,
+                &[
+                    ("subject", text(&subject).as_str()),
+                    ("username", text(&subscription.user).as_str()),
+                    ("period", period),
+                    ("frequency", frequency.as_str()),
+                    ("articles", articles),
+                    ("notifications", notifications),
+                    ("settings", text(&settings).as_str()),
+                    ("unsubscribe", text(&unsubscribe).as_str()),
+                ],
+            )
+        };
+        Message {
+            to: subscription.email.clone(),
+            html: fill(HTML_TEMPLATE, &articles_html, &notifications_html, true),
+            text: fill(TEXT_TEMPLATE, &articles_text, &notifications_text, false),
+            subject,
+            // One-click unsubscribing (RFC 8058)
+            headers: vec![
+                ("List-Unsubscribe".into(), format!("<{}>", unsubscribe)),
+                (
+                    "List-Unsubscribe-Post".into(),
+                    "List-Unsubscribe=One-Click".into(),
+                ),
+            ],
+        }
+    }
+}
+
+/// Sends the digests that are due. Digests with nothing new are skipped,
+/// but still count as sent.
+async fn send_due(mailer: &Mailer, base: &str) -> Result<(), sqlx::Error> {
+    for subscription in DigestSubscription::due(CHECK_HOURS).await? {
+        let Some(days) = subscription.frequency.days() else {
+            continue;
+        };
+        let digest = match Digest::collect(&subscription, days).await {
+            Ok(digest) => digest,
+            Err(e) => {
+                // Tried again on the next round, without holding up the others
+                tracing::error!("could not collect digest of {}: {:?}", subscription.user, e);
+                continue;
+            }
+        };
+        if !digest.is_empty() {
+            let message = digest.message(base, &subscription);
+            if let Err(e) = mailer.send(&message).await {
+                // Tried again on the next round
+                tracing::error!("could not send digest to {}: {:?}", subscription.user, e);
+                continue;
+            }
+        }
+        if let Err(e) = DigestSubscription::mark_sent(&subscription.user).await {
+            tracing::error!("digest sent to {} not marked: {:?}", subscription.user, e);
+        }
+    }
+    Ok(())
+}
+
+/// Starts sending the digests in the background, if there is a mailer
+/// configured. `SITE_URL` has to be set as well, like for the feeds.
+pub fn spawn() {
+    let Some(mailer) = Mailer::configured() else {
+        tracing::info!("no mailer configured, email digests are not sent");
+        return;
+    };
+    if !crate::site::url_configured() {
+        tracing::error!("SITE_URL is not set, email digests are not sent");
+        return;
+    }
+    let base = crate::site::base_url();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(CHECK_HOURS as u64 * 3600));
+        loop {
+            interval.tick().await;
+            if let Err(e) = send_due(&mailer, &base).await {
+                tracing::error!("failed to send digests: {:?}", e);
+            }
+        }
+    });
+}
+
+fn unsubscribe_page(message: &str, form: Option<&str>) -> String {
+    let form = form
+        .map(|action| {
+            format!(
+                r#"<form method="post" action="{}"><button type="submit">Unsubscribe</button></form>"#,
+                escape(action)
+            )
+        })
+        .unwrap_or_default();
+    format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><title>Email digests — Conduit</title></head>
+<body><p>{}</p>{}<p><a href="/settings">Settings</a></p></body></html>
+"#,
+        message, form
+    )
+}
+
+/// `/unsubscribe/<token>` confirmation page. Unsubscribing doesn't happen on
+/// GET, as link checkers of mail providers open the links too.
+pub async fn unsubscribe_form(Path(token): Path<String>) -> Html<String> {
+    Html(unsubscribe_page(
+        "Unsubscribe from the email digests?",
+        Some(&format!("/unsubscribe/{}", token)),
+    ))
+}
+
+/// POST `/unsubscribe/<token>`, from the confirmation page or as the
+/// one-click unsubscribe of the mail client
+pub async fn unsubscribe(Path(token): Path<String>) -> Response {
+    match DigestSubscription::unsubscribe(&token).await {
+        Ok(true) => Html(unsubscribe_page(
+            "You won't get email digests anymore.",
+            None,
+        ))
+        .into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Html(unsubscribe_page("The unsubscribe link is not valid.", None)),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("failed to unsubscribe: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{article::Article, user::Profile};
+
+    fn digest() -> Digest {
+        let article = Article {
+            slug: "rust-and-wasm".into(),
+            title: "Rust & WASM".into(),
+            description: "Compiling to the browser".into(),
+            body: String::new(),
+            created_at: "2024-06-01 10:00:00".into(),
+            updated_at: None,
+            tags: Vec::new(),
+            favorited: false,
+            favorites_count: 0,
+            comments_count: 0,
+            bookmarked: false,
+            bookmark_read: false,
+            reactions: Default::default(),
+            author: Profile {
+                username: "bob".into(),
+                ..Default::default()
+            },
+        };
+        Digest {
+            feed: Feed {
+                articles: vec![article],
+                count: 3,
+                ..Default::default()
+            },
+            notifications: vec![(
+                "carol commented on your article".into(),
+                "/article/hello#comment-1".into(),
+            )],
+        }
+    }
+
+    fn subscription() -> DigestSubscription {
+        DigestSubscription {
+            user: "alice".into(),
+            email: "alice@example.com".into(),
+            frequency: DigestFrequency::Daily,
+            token: "abc123".into(),
+        }
+    }
+
+    #[test]
+    fn message_parts() {
+        let message = digest().message("https://conduit.test", &subscription());
+        assert_eq!(message.to, "alice@example.com");
+        assert_eq!(message.subject, "Your daily Conduit digest");
+
+        assert!(message
+            .text
+            .starts_with("Hi alice, here is what happened today."));
+        assert!(message.text.contains(
+            "* Rust & WASM by bob\n  Compiling to the browser\n  https://conduit.test/article/rust-and-wasm"
+        ));
+        assert!(message
+            .text
+            .contains("2 more in your feed: https://conduit.test/feed"));
+        assert!(message.text.contains(
+            "* carol commented on your article\n  https://conduit.test/article/hello#comment-1"
+        ));
+        assert!(message
+            .text
+            .contains("Unsubscribe: https://conduit.test/unsubscribe/abc123"));
+
+        assert!(message.html.contains(
+            r#"<a href="https://conduit.test/article/rust-and-wasm">Rust &amp; WASM</a>"#
+        ));
+        assert!(message
+            .html
+            .contains("https://conduit.test/unsubscribe/abc123"));
+        assert!(!message.html.contains("{{"));
+        assert!(!message.text.contains("{{"));
+    }
+
+    #[tokio::test]
+    async fn delivered_to_outbox() {
+        let dir = std::env::temp_dir().join(format!("conduit-outbox-{}", std::process::id()));
+        let message = digest().message("https://conduit.test", &subscription());
+        Mailer::Outbox(dir.clone())
+            .send(&message)
+            .await
+            .expect("sent");
+
+        let mut files = std::fs::read_dir(&dir)
+            .expect("outbox")
+            .map(|entry| entry.expect("outbox entry").path())
+            .collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        let file = files.pop().unwrap();
+        assert_eq!(file.extension().and_then(|e| e.to_str()), Some("eml"));
+        let sent = std::fs::read_to_string(&file).expect("sent message");
+        std::fs::remove_dir_all(&dir).expect("removed outbox");
+
+        let (headers, body) = sent.split_once("\r\n\r\n").expect("headers and body");
+        let headers: Vec<_> = headers.split("\r\n").collect();
+        assert!(headers.contains(&"To: alice@example.com"));
+        assert!(headers.contains(&"Subject: Your daily Conduit digest"));
+        assert!(headers.contains(&"List-Unsubscribe: <https://conduit.test/unsubscribe/abc123>"));
+        assert!(headers.contains(&"List-Unsubscribe-Post: List-Unsubscribe=One-Click"));
+        assert!(headers
+            .iter()
+            .any(|h| h.starts_with("Content-Type: multipart/alternative;")));
+
+        let text = body
+            .find("Content-Type: text/plain; charset=utf-8")
+            .expect("text part");
+        let html = body
+            .find("Content-Type: text/html; charset=utf-8")
+            .expect("html part");
+        assert!(text < html, "the preferred HTML part should be last");
+        assert!(body[text..html].contains("Hi alice, here is what happened today."));
+        assert!(body[html..].contains("<h2>New articles</h2>"));
+        assert!(body.ends_with("\r\n--=_conduit_alternative--\r\n"));
+    }
+}
@@ -0,0 +1,152 @@
+//! Sending email, through sendmail or into a local outbox directory.
+//!
+//! Configured at build time with `MAIL_OUTBOX` for a directory to write the
+//! messages into, e.g. when testing, or otherwise with `SENDMAIL` for the
+//! sendmail compatible program to deliver them with.
+
+use std::{
+    path::PathBuf,
+    process::Stdio,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use tokio::io::AsyncWriteExt;
+
+/// Sender of the emails unless configured with `MAIL_FROM`
+const DEFAULT_FROM: &str = "Conduit <noreply@localhost>";
+
+/// Separates the parts of a multipart message. Quoted printable text never
+/// contains `=_`, so the boundary can't appear in the parts.
+const BOUNDARY: &str = "=_conduit_alternative";
+
+/// Email with plain text and HTML versions of the body
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+    /// Further headers, e.g. for unsubscribing
+    pub headers: Vec<(String, String)>,
+}
+
+/// Removes line breaks, which would start new headers
+fn header_value(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+/// Encodes text as quoted printable (RFC 2045), with CRLF line endings
+fn quoted_printable(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 11 / 10);
+    for line in text.lines() {
+        let bytes = line.as_bytes();
+        let mut length = 0;
+        for (i, &b) in bytes.iter().enumerate() {
+            // Whitespace at the end of a line would be lost in transit
+            let space = matches!(b, b' ' | b'\t') && i + 1 != bytes.len();
+            let encoded = if space || matches!(b, b'!'..=b'~') && b != b'=' {
+                (b as char).to_string()
+            } else {
+                format!("={:02X}", b)
+            };
+            // Soft line break to keep within the 76 characters
+            if length + encoded.len() > 75 {
+                out.push_str("=\r\n");
+                length = 0;
+            }
+            out.push_str(&encoded);
+            length += encoded.len();
+        }
+        out.push_str("\r\n");
+    }
+    out
+}
+
+impl Message {
+    /// The message in the internet message format, with a text and HTML
+    /// alternative
+    pub fn format(&self, from: &str) -> String {
+        let mut headers = vec![
+            ("From".to_owned(), from.to_owned()),
+            ("To".to_owned(), self.to.clone()),
+            ("Subject".to_owned(), self.subject.clone()),
+            ("Date".to_owned(), chrono::Utc::now().to_rfc2822()),
+            ("MIME-Version".to_owned(), "1.0".to_owned()),
+        ];
+        headers.extend(self.headers.iter().cloned());
+        headers.push((
+            "Content-Type".to_owned(),
+            format!(r#"multipart/alternative; boundary="{}""#, BOUNDARY),
+        ));
+
+        let mut out = String::new();
+        for (name, value) in headers {
+            out.push_str(&format!("{}: {}\r\n", name, header_value(&value)));
+        }
+        for (content_type, body) in [("text/plain", &self.text), ("text/html", &self.html)] {
+            out.push_str(&format!(
+                "\r\n--{}\r\nContent-Type: {}; charset=utf-8\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\n",
+                BOUNDARY, content_type
+            ));
+            out.push_str(&quoted_printable(body));
+        }
+        out.push_str(&format!("\r\n--{}--\r\n", BOUNDARY));
+        out
+    }
+}
+
+/// Where the emails go
+#[derive(Debug, Clone)]
+pub enum Mailer {
+    /// Written as `.eml` files into the directory instead of sending
+    Outbox(PathBuf),
+    /// Delivered by the sendmail compatible program
+    Sendmail(PathBuf),
+}
+
+impl Mailer {
+    /// The configured mailer, if there is one
+    pub fn configured() -> Option<Self> {
+        if let Some(dir) = std::option_env!("MAIL_OUTBOX") {
+            return Some(Self::Outbox(dir.into()));
+        }
+        std::option_env!("SENDMAIL").map(|program| Self::Sendmail(program.into()))
+    }
+
+    pub async fn send(&self, message: &Message) -> std::io::Result<()> {
+        let from = std::option_env!("MAIL_FROM").unwrap_or(DEFAULT_FROM);
+        let formatted = message.format(from);
+        match self {
+            Self::Outbox(dir) => {
+                // Keeps the names unique within the same second
+                static SENT: AtomicU64 = AtomicU64::new(0);
+                tokio::fs::create_dir_all(dir).await?;
+                let name = format!(
+                    "{}-{}.eml",
+                    chrono::Utc::now().format("%Y%m%d%H%M%S"),
+                    SENT.fetch_add(1, Ordering::Relaxed)
+                );
+                tokio::fs::write(dir.join(name), formatted).await
+            }
+            Self::Sendmail(program) => {
+                // Recipients are read from the headers
+                let mut child = tokio::process::Command::new(program)
+                    .args(["-t", "-i"])
+                    .stdin(Stdio::piped())
+                    .spawn()?;
+                let mut stdin = child.stdin.take().expect("stdin is piped");
+                stdin.write_all(formatted.as_bytes()).await?;
+                drop(stdin);
+                let status = child.wait().await?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(std::io::Error::other(format!(
+                        "sendmail failed: {}",
+                        status
+                    )))
+                }
+            }
+        }
+    }
+}
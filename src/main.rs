@@ -15,6 +15,7 @@ async fn main() {
         .init();
 
     demo_app::db::init().await;
+    demo_app::digest::spawn();
 
     // Setting get_configuration(None) means we'll be using cargo-leptos's env values
     // For deployment these variables are:
@@ -29,22 +30,44 @@ async fn main() {
     async fn get_raw_md(
         Path((author, slug)): Path<(String, String)>,
     ) -> Result<String, http::StatusCode> {
-        sqlx::query_scalar!(
+        let body = sqlx::query_scalar!(
             "select body from article where author = ? and slug = ?",
             author,
             slug
         )
         .fetch_one(demo_app::db::get())
         .await
-        .map_err(|_| http::StatusCode::NOT_FOUND)
+        .map_err(|_| http::StatusCode::NOT_FOUND)?;
+        let users = demo_app::models::mention::resolve([body.as_str()])
+            .await
+            .map_err(|e| {
+                tracing::error!("could not resolve mentions: {:?}", e);
+                http::StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        Ok(demo_app::markdown::link_mentions(&body, &users))
     }
 
     // build our application with a route
     let app = Router::new()
         .leptos_routes(&leptos_options, routes, App)
         .route("/raw/article/:author/:slug", get(get_raw_md))
+        .route(
+            "/article/:slug/comments/events",
+            get(demo_app::realtime::comment_events),
+        )
+        .route("/sitemap.xml", get(demo_app::sitemap::sitemap))
+        .route("/sitemap/:page", get(demo_app::sitemap::sitemap_page))
+        .route("/robots.txt", get(demo_app::sitemap::robots))
+        .route(
+            "/unsubscribe/:token",
+            get(demo_app::digest::unsubscribe_form).post(demo_app::digest::unsubscribe),
+        )
+        .merge(demo_app::syndication::routes())
         .fallback(file_and_error_handler)
         .layer(CompressionLayer::new())
+        .layer(axum::middleware::from_fn(
+            demo_app::redirect::old_slug_middleware,
+        ))
         .layer(TraceLayer::new_for_http())
         .layer(axum::middleware::from_fn(auth::server::auth_middleware))
         .with_state(leptos_options);
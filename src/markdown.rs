@@ -0,0 +1,346 @@
+//! Server side rendering of markdown into HTML.
+
+use std::{collections::HashSet, ops::Range};
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+
+/// Whether the link is safe to follow, i.e. not e.g. `javascript:`
+fn is_safe_url(url: &str) -> bool {
+    match url.split_once(':') {
+        // Relative links can still have ':' later on
+        Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => {
+            matches!(
+                scheme.to_ascii_lowercase().as_str(),
+                "http" | "https" | "mailto"
+            )
+        }
+        _ => true,
+    }
+}
+
+/// Neutralise the parts of markdown that could be used for injecting content
+fn sanitize(event: Event<'_>) -> Event<'_> {
+    match event {
+        // Raw HTML is shown as text, instead of letting it through
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !is_safe_url(&dest_url) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: CowStr::Borrowed("#"),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !is_safe_url(&dest_url) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: CowStr::Borrowed(""),
+            title,
+            id,
+        }),
+        event => event,
+    }
+}
+
+/// Escapes text for a quoted attribute value
+fn escape_attribute(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Limits markdown to what is allowed in comments. Headings become paragraphs,
+/// images their alt text and quotes their content. Links are marked as user
+/// generated, so they don't pass on ranking.
+fn restrict(event: Event<'_>) -> Option<Event<'_>> {
+    Some(match event {
+        Event::Start(Tag::Heading { .. }) => Event::Start(Tag::Paragraph),
+        Event::End(TagEnd::Heading { .. }) => Event::End(TagEnd::Paragraph),
+        Event::Start(Tag::Image { .. } | Tag::BlockQuote { .. })
+        | Event::End(TagEnd::Image | TagEnd::BlockQuote { .. })
+        | Event::Rule => return None,
+        Event::Start(Tag::Link {
+            dest_url, title, ..
+        }) => {
+            let title = if title.is_empty() {
+                String::new()
+            } else {
+                format!(r#" title="{}""#, escape_attribute(&title))
+            };
+            Event::Html(
+                format!(
+                    r#"<a href="{}"{} rel="nofollow ugc">"#,
+                    escape_attribute(&dest_url),
+                    title
+                )
+                .into(),
+            )
+        }
+        Event::End(TagEnd::Link) => Event::Html("</a>".into()),
+        event => event,
+    })
+}
+
+/// Characters a mentionable username consists of
+fn is_username_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Byte ranges of the `@username` mentions in the markdown, including the `@`.
+///
+/// Code, links, images and raw HTML are skipped, as are `@`s preceded by a
+/// word, like in email addresses. So are `@`s after `!`, `\` or `[`, which
+/// would turn a linked mention into an image, an escape or a nested link.
+fn mention_ranges(markdown: &str) -> Vec<Range<usize>> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let mut skipped = Vec::new();
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        match event {
+            // The range of a start covers the whole element
+            Event::Start(
+                Tag::CodeBlock(_) | Tag::Link { .. } | Tag::Image { .. } | Tag::HtmlBlock,
+            )
+            | Event::Code(_)
+            | Event::Html(_)
+            | Event::InlineHtml(_) => skipped.push(range),
+            _ => {}
+        }
+    }
+
+    let mut mentions = Vec::new();
+    let mut previous = None;
+    for (start, c) in markdown.char_indices() {
+        let after_word = previous.is_some_and(|p: char| p.is_alphanumeric() || p == '_');
+        let after_syntax = matches!(previous, Some('!' | '\\' | '['));
+        previous = Some(c);
+        if c != '@' || after_word || after_syntax || skipped.iter().any(|r| r.contains(&start)) {
+            continue;
+        }
+        let name = &markdown[start + 1..];
+        let len = name.find(|c| !is_username_char(c)).unwrap_or(name.len());
+        // A trailing dash is more likely punctuation than part of the name
+        let len = name[..len].trim_end_matches('-').len();
+        if len > 0 {
+            mentions.push(start..start + 1 + len);
+        }
+    }
+    mentions
+}
+
+/// Usernames mentioned in the markdown, without duplicates
+pub fn mentions(markdown: &str) -> Vec<String> {
+    let mut names: Vec<String> = mention_ranges(markdown)
+        .into_iter()
+        .map(|range| markdown[range.start + 1..range.end].to_owned())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// Turns the mentions of the users into links to their profiles. Mentions of
+/// anyone else, e.g. of a username that doesn't exist, are left as they are.
+pub fn link_mentions(markdown: &str, users: &HashSet<String>) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut last = 0;
+    for range in mention_ranges(markdown) {
+        let name = &markdown[range.start + 1..range.end];
+        if !users.contains(name) {
+            continue;
+        }
+        out.push_str(&markdown[last..range.start]);
+        out.push_str(&format!("[@{}](/profile/{})", name, name));
+        last = range.end;
+    }
+    out.push_str(&markdown[last..]);
+    out
+}
+
+/// Renders a comment body, which allows a subset of markdown: emphasis,
+/// inline code, code blocks, lists and links. The mentions of the users are
+/// linked to their profiles.
+pub fn comment_html(markdown: &str, users: &HashSet<String>) -> String {
+    let markdown = &link_mentions(markdown, users);
+    let parser = Parser::new_ext(markdown, Options::empty())
+        .map(sanitize)
+        .filter_map(restrict);
+    let mut out = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut out, parser);
+    out
+}
+
+/// Renders an article body, as it would be shown on the article page. The
+/// mentions of the users are linked to their profiles.
+pub fn article_html(markdown: &str, users: &HashSet<String>) -> String {
+    let markdown = &link_mentions(markdown, users);
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let parser = Parser::new_ext(markdown, options).map(sanitize);
+    let mut out = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut out, parser);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsafe_link_urls_are_dropped() {
+        for url in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "data:text/html;base64,PHNjcmlwdD4=",
+            "vbscript:msgbox",
+        ] {
+            let markdown = format!("[click]({})", url);
+            for html in [
+                article_html(&markdown, &HashSet::new()),
+                comment_html(&markdown, &HashSet::new()),
+            ] {
+                assert!(html.contains(r##"href="#""##), "{}", html);
+                assert!(!html.contains(url), "{}", html);
+            }
+        }
+    }
+
+    #[test]
+    fn unsafe_image_urls_are_dropped() {
+        let html = article_html(
+            "![x](javascript:alert(1)) ![y](data:image/svg+xml,<svg/>)",
+            &HashSet::new(),
+        );
+        assert!(!html.contains("javascript:"), "{}", html);
+        assert!(!html.contains("data:"), "{}", html);
+        assert!(html.contains(r#"<img src="" alt="x""#), "{}", html);
+    }
+
+    #[test]
+    fn safe_urls_are_kept() {
+        let html = article_html(
+            "[a](https://example.com/x?y=1) [b](/article/c) [c](mailto:a@b.c)",
+            &HashSet::new(),
+        );
+        assert!(
+            html.contains(r#"href="https://example.com/x?y=1""#),
+            "{}",
+            html
+        );
+        assert!(html.contains(r#"href="/article/c""#), "{}", html);
+        assert!(html.contains(r#"href="mailto:a@b.c""#), "{}", html);
+    }
+
+    #[test]
+    fn raw_html_is_escaped() {
+        let html = article_html(
+            "<script>alert(1)</script>\n\nSee <img src=x onerror=alert(1)>",
+            &HashSet::new(),
+        );
+        assert!(!html.contains("<script"), "{}", html);
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(
+            html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("&lt;img src=x onerror=alert(1)&gt;"),
+            "{}",
+            html
+        );
+
+        let html = comment_html("<b onclick=\"x()\">bold</b>", &HashSet::new());
+        assert!(!html.contains("<b"), "{}", html);
+        assert!(
+            html.contains("&lt;b onclick=&quot;x()&quot;&gt;"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn comment_links_are_user_generated() {
+        assert_eq!(
+            comment_html("[a](https://example.com)", &HashSet::new()),
+            "<p><a href=\"https://example.com\" rel=\"nofollow ugc\">a</a></p>\n"
+        );
+        // The title can't break out of its attribute
+        let html = comment_html(
+            r#"[a](https://example.com 'x" onmouseover="y')"#,
+            &HashSet::new(),
+        );
+        assert!(
+            html.contains(r#"title="x&quot; onmouseover=&quot;y" rel="nofollow ugc""#),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn comments_have_no_images_nor_headings() {
+        let html = comment_html(
+            "# Title\n\n![a cat](https://example.com/cat.png)\n\n> quote\n\n---",
+            &HashSet::new(),
+        );
+        assert!(!html.contains("<h1"), "{}", html);
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(!html.contains("<blockquote"), "{}", html);
+        assert!(!html.contains("<hr"), "{}", html);
+        assert!(html.contains("<p>Title</p>"), "{}", html);
+        assert!(html.contains("a cat"), "{}", html);
+        assert!(html.contains("quote"), "{}", html);
+
+        // Articles can have them
+        let html = article_html(
+            "# Title\n\n![a cat](https://example.com/cat.png)",
+            &HashSet::new(),
+        );
+        assert!(html.contains("<h1>Title</h1>"), "{}", html);
+        assert!(
+            html.contains(r#"<img src="https://example.com/cat.png" alt="a cat""#),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn only_users_are_linked() {
+        let users = HashSet::from(["alice".to_owned()]);
+        assert_eq!(
+            link_mentions("@alice and @nobody, `@alice`", &users),
+            "[@alice](/profile/alice) and @nobody, `@alice`"
+        );
+        let html = comment_html("hi @nobody", &users);
+        assert!(!html.contains("<a"), "{}", html);
+    }
+
+    #[test]
+    fn mentions_after_markdown_syntax_are_not_linked() {
+        let users = HashSet::from(["alice".to_owned()]);
+        for markdown in [
+            "!@alice",
+            r"\@alice",
+            "[@alice]",
+            "[@alice]: https://example.com",
+        ] {
+            assert_eq!(link_mentions(markdown, &users), markdown);
+            assert!(mentions(markdown).is_empty(), "{}", markdown);
+        }
+
+        let html = article_html("hi !@alice", &users);
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(html.contains("!@alice"), "{}", html);
+
+        // Neither in the text of a link
+        let html = comment_html("[hi @alice](https://example.com)", &users);
+        assert_eq!(html.matches("<a ").count(), 1, "{}", html);
+        assert!(!html.contains("/profile/alice"), "{}", html);
+    }
+}
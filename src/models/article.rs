@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
-use super::user::Profile;
+use super::{reaction::Reactions, user::Profile};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Article {
@@ -15,6 +17,13 @@ pub struct Article {
     pub tags: Vec<String>,
     pub favorited: bool,
     pub favorites_count: u32,
+    /// Comments that aren't deleted, replies included
+    pub comments_count: u32,
+    /// In the viewer's reading list
+    pub bookmarked: bool,
+    /// Opened by the viewer since bookmarking it
+    pub bookmark_read: bool,
+    pub reactions: Reactions,
     pub author: Profile,
 }
 
@@ -24,34 +33,384 @@ pub struct ArticleEditFields {
     pub description: String,
     pub body: String,
     pub tags: Vec<String>,
+    /// Stored revision the fields were loaded from
+    pub revision: i64,
 }
 
+/// Reasons an article edit is rejected, without it being an actual error
 #[derive(Serialize, Deserialize, Clone)]
+pub enum EditRejection {
+    Invalid(Vec<String>),
+    /// Someone saved the article after the editor was loaded. Contains the
+    /// currently stored version, so the changes can be reconciled.
+    Conflict(ArticleEditFields),
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct Feed {
     pub articles: Vec<Article>,
     pub count: u32,
+    /// Highlighted matches by article slug, for search results
+    pub snippets: HashMap<String, String>,
+    /// Why the articles are in the personal feed, by article slug
+    pub reasons: HashMap<String, FeedReason>,
+    /// Cursor for the previous (newer) page, if there is one
+    pub prev: Option<String>,
+    /// Cursor for the next (older) page, if there is one
+    pub next: Option<String>,
+}
+
+/// Why an article is in the personal feed of the user
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum FeedReason {
+    /// Written by a user they follow
+    Author(String),
+    /// Has a tag they follow
+    Tag(String),
+}
+
+/// Opaque cursor from [`Feed`] for continuing in either direction
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum FeedCursor {
+    After(String),
+    Before(String),
 }
 
 #[derive(Debug)]
 pub struct FeedOptions {
-    pub offset: u32,
+    pub cursor: Option<FeedCursor>,
     pub limit: u8,
+    /// Order of the articles. Search results have their own order.
+    pub sort: FeedSort,
     pub user: Option<String>,
+    /// Further conditions on top of the ones of the feed itself
+    pub filter: FeedFilter,
 }
 
 impl Default for FeedOptions {
     fn default() -> Self {
         Self {
-            offset: 0,
+            cursor: None,
             limit: 20,
+            sort: FeedSort::default(),
             user: None,
+            filter: FeedFilter::default(),
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl FeedOptions {
+    /// Hides the authors the user has muted, unless the feed is explicitly
+    /// of some authors, like on their profile, or of a reading list, where the
+    /// user has saved the articles themselves.
+    pub(super) fn push_muted_condition(
+        &self,
+        builder: &mut sqlx::QueryBuilder<'_, sqlx::Sqlite>,
+        filter: &FeedFilter,
+    ) {
+        let Some(user) = &self.user else {
+            return;
+        };
+        let explicit = |f: &FeedFilter| !f.authors.is_empty() || f.bookmarked_by.is_some();
+        if !explicit(filter) && !explicit(&self.filter) {
+            builder
+                .push(" and article.author not in (select muted from mute where user = ")
+                .push_bind(user.clone())
+                .push(")");
+        }
+    }
+}
+
+/// How the tags of a filter are combined
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TagMatch {
+    /// Article has at least one of the tags
+    #[default]
+    Any,
+    /// Article has all of the tags
+    All,
+}
+
+/// Order of the articles in a feed
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FeedSort {
+    #[default]
+    Newest,
+    /// By the number of reactions to the article
+    MostReacted,
+}
+
+impl FeedSort {
+    pub fn from_param(param: &str) -> Self {
+        match param {
+            "reacted" => Self::MostReacted,
+            _ => Self::Newest,
+        }
+    }
+
+    pub fn param(self) -> &'static str {
+        match self {
+            Self::Newest => "newest",
+            Self::MostReacted => "reacted",
+        }
+    }
+}
+
+/// Conditions for articles in a feed. Empty filter matches everything, and
+/// otherwise all of the given conditions must match.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+// Empty lists are left out of the query string encoding of server function arguments
+#[serde(default)]
+pub struct FeedFilter {
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+    pub excluded_tags: Vec<String>,
+    /// Written by any of the authors
+    pub authors: Vec<String>,
+    /// Written by someone the user follows, or with a tag they follow
+    pub followed_by: Option<String>,
+    pub favorited_by: Option<String>,
+    /// In the reading list of the user. Bookmarks are private, so this is
+    /// only set on the server.
+    #[serde(skip)]
+    pub bookmarked_by: Option<String>,
+    /// Created on or after the date (YYYY-MM-DD)
+    pub since: Option<String>,
+    /// Created on or before the date (YYYY-MM-DD)
+    pub until: Option<String>,
+    /// Created within the given number of days
+    pub within_days: Option<u32>,
+}
+
+impl FeedFilter {
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn tag_match(mut self, tag_match: TagMatch) -> Self {
+        self.tag_match = tag_match;
+        self
+    }
+
+    pub fn exclude_tag(mut self, tag: impl Into<String>) -> Self {
+        self.excluded_tags.push(tag.into());
+        self
+    }
+
+    pub fn author(mut self, author: impl Into<String>) -> Self {
+        self.authors.push(author.into());
+        self
+    }
+
+    pub fn followed_by(mut self, user: impl Into<String>) -> Self {
+        self.followed_by = Some(user.into());
+        self
+    }
+
+    pub fn favorited_by(mut self, user: impl Into<String>) -> Self {
+        self.favorited_by = Some(user.into());
+        self
+    }
+
+    pub fn bookmarked_by(mut self, user: impl Into<String>) -> Self {
+        self.bookmarked_by = Some(user.into());
+        self
+    }
+
+    pub fn since(mut self, date: impl Into<String>) -> Self {
+        self.since = Some(date.into());
+        self
+    }
+
+    pub fn until(mut self, date: impl Into<String>) -> Self {
+        self.until = Some(date.into());
+        self
+    }
+
+    pub fn within_days(mut self, days: u32) -> Self {
+        self.within_days = Some(days);
+        self
+    }
+
+    /// Adds the conditions to a query with `article` table, and an existing `where` clause.
+    #[cfg(feature = "ssr")]
+    pub(super) fn push_conditions(&self, builder: &mut sqlx::QueryBuilder<'_, sqlx::Sqlite>) {
+        if !self.tags.is_empty() {
+            match self.tag_match {
+                TagMatch::Any => {
+                    builder.push(" and article.slug in (select article from tag where tag in (");
+                    let mut list = builder.separated(", ");
+                    for tag in &self.tags {
+                        list.push_bind(tag.clone());
+                    }
+                    builder.push("))");
+                }
+                TagMatch::All => {
+                    for tag in &self.tags {
+                        builder
+                            .push(" and article.slug in (select article from tag where tag = ")
+                            .push_bind(tag.clone())
+                            .push(")");
+                    }
+                }
+            }
+        }
+        if !self.excluded_tags.is_empty() {
+            builder.push(" and article.slug not in (select article from tag where tag in (");
+            let mut list = builder.separated(", ");
+            for tag in &self.excluded_tags {
+                list.push_bind(tag.clone());
+            }
+            builder.push("))");
+        }
+        if !self.authors.is_empty() {
+            builder.push(" and article.author in (");
+            let mut list = builder.separated(", ");
+            for author in &self.authors {
+                list.push_bind(author.clone());
+            }
+            builder.push(")");
+        }
+        if let Some(user) = &self.followed_by {
+            builder
+                .push(" and (article.author in (select followed from follow where follower = ")
+                .push_bind(user.clone())
+                .push(") or article.slug in (")
+                .push(
+                    "select article from tag join tag_follow using (tag) where tag_follow.user = ",
+                )
+                .push_bind(user.clone())
+                .push("))");
+        }
+        if let Some(user) = &self.favorited_by {
+            builder
+                .push(" and article.slug in (select article from favorite where user = ")
+                .push_bind(user.clone())
+                .push(")");
+        }
+        if let Some(user) = &self.bookmarked_by {
+            builder
+                .push(" and article.slug in (select article from bookmark where user = ")
+                .push_bind(user.clone())
+                .push(")");
+        }
+        if let Some(date) = &self.since {
+            builder
+                .push(" and article.created_at >= date(")
+                .push_bind(date.clone())
+                .push(")");
+        }
+        if let Some(date) = &self.until {
+            builder
+                .push(" and article.created_at < date(")
+                .push_bind(date.clone())
+                .push(", '+1 day')");
+        }
+        if let Some(days) = self.within_days {
+            builder
+                .push(" and article.created_at >= datetime('now', ")
+                .push_bind(format!("-{} days", days))
+                .push(")");
         }
     }
 }
 
+/// Position in a feed, which is encoded into the cursors
+#[cfg(feature = "ssr")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum FeedPosition {
+    /// Article in a feed ordered by creation time
+    Article { created_at: String, slug: String },
+    /// Index in a feed with some other order, e.g. search relevance
+    Offset(u32),
+}
+
+/// Opaque form of a cursor, so clients don't start depending on its contents
+#[cfg(feature = "ssr")]
+pub(super) fn encode_token(raw: &str) -> String {
+    raw.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(feature = "ssr")]
+pub(super) fn decode_token(token: &str) -> Option<String> {
+    let bytes = (0..token.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(token.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(feature = "ssr")]
+impl FeedPosition {
+    const SEPARATOR: char = '\u{1f}';
+
+    fn of(article: &Article) -> Self {
+        Self::Article {
+            created_at: article.created_at.clone(),
+            slug: article.slug.clone(),
+        }
+    }
+
+    pub(super) fn token(&self) -> String {
+        let raw = match self {
+            Self::Article { created_at, slug } => {
+                format!("a{sep}{created_at}{sep}{slug}", sep = Self::SEPARATOR)
+            }
+            Self::Offset(offset) => format!("o{sep}{offset}", sep = Self::SEPARATOR),
+        };
+        encode_token(&raw)
+    }
+
+    pub(super) fn from_token(token: &str) -> Option<Self> {
+        let raw = decode_token(token)?;
+        let mut parts = raw.split(Self::SEPARATOR);
+        match (parts.next()?, parts.next()?, parts.next()) {
+            ("a", created_at, Some(slug)) => Some(Self::Article {
+                created_at: created_at.to_owned(),
+                slug: slug.to_owned(),
+            }),
+            ("o", offset, None) => offset.parse().ok().map(Self::Offset),
+            _ => None,
+        }
+    }
+
+    /// Start of the page for feeds paged by offset, instead of position
+    pub(super) fn page_offset(cursor: Option<&FeedCursor>, limit: u8) -> u32 {
+        let offset = |token: &str| match Self::from_token(token) {
+            Some(Self::Offset(offset)) => offset,
+            _ => 0,
+        };
+        match cursor {
+            Some(FeedCursor::After(token)) => offset(token),
+            Some(FeedCursor::Before(token)) => offset(token).saturating_sub(limit.into()),
+            None => 0,
+        }
+    }
+}
+
+/// Cursor continuing after the article, e.g. to start from a specific point in a feed
+#[cfg(feature = "ssr")]
+pub fn cursor_after(created_at: &str, slug: &str) -> FeedCursor {
+    let position = FeedPosition::Article {
+        created_at: created_at.to_owned(),
+        slug: slug.to_owned(),
+    };
+    FeedCursor::After(position.token())
+}
+
+/// Upper bound for the generated part of a slug, collision suffix not included
+#[cfg(feature = "ssr")]
+const MAX_SLUG_LEN: usize = 80;
+
+/// Slugs that would be confusing, or could later clash with routes
+#[cfg(feature = "ssr")]
+const RESERVED_SLUGS: &[&str] = &["new", "edit", "editor", "feed", "search", "article"];
+
 #[cfg(feature = "ssr")]
 #[derive(sqlx::FromRow)]
-struct ArticleRow {
+pub(super) struct ArticleRow {
     pub slug: String,
     pub title: String,
     pub description: String,
@@ -78,82 +437,127 @@ impl From<ArticleRow> for Article {
             tags: vec![],       // TODO
             favorited: false,   // TODO
             favorites_count: 0, // TODO
+            comments_count: 0,
+            bookmarked: false,
+            bookmark_read: false,
+            reactions: Reactions::default(),
             author: Profile {
                 username: row.author,
                 bio: row.bio,
                 image: row.image,
                 following: false, // TODO
+                ..Default::default()
             },
         }
     }
 }
 
 #[cfg(feature = "ssr")]
-macro_rules! feed_query {
-    ($query:literal, $options:expr, $($args:tt)*) => ({
-        let count: i32 = sqlx::query_scalar(
-            concat!("select count(*) from article join user on article.author = user.username ", $query)
-        )
-        $(.bind($args))*
-        .fetch_optional(crate::db::get())
-        .await
-        .ok().flatten().unwrap_or_default();
-        let articles = sqlx::query_as::<_, ArticleRow>(
-            concat!("
-                select article.*, user.bio, user.image
-                from article join user on article.author = user.username ",
-                $query,
-                " order by article.created_at desc limit ? offset ?")
-        )
-        $(.bind($args))*
-        .bind($options.limit)
-        .bind($options.offset)
-        .fetch_all(crate::db::get()).await?;
-        fill_feed_details(articles, count as u32, $options).await
-    })
-}
-
-#[cfg(feature = "ssr")]
-async fn fill_feed_details(
+pub(super) async fn fill_feed_details(
     articles: Vec<ArticleRow>,
     count: u32,
     options: &FeedOptions,
 ) -> Result<Feed, sqlx::Error> {
     // FIXME: sqlx does not support subqueries (at least properly).
     // Thus we need to fill in some details here with extra queries.
-    use std::collections::{HashMap, HashSet};
+    // The queries are limited to the articles and authors on the page, which
+    // are passed in as JSON arrays to keep the number of queries constant.
+    use std::collections::HashSet;
+
+    let slugs: Vec<_> = articles.iter().map(|a| a.slug.as_str()).collect();
+    let slugs = serde_json::to_string(&slugs).expect("slugs as json");
+    let authors: HashSet<_> = articles.iter().map(|a| a.author.as_str()).collect();
+    let authors = serde_json::to_string(&authors).expect("authors as json");
 
     let mut tags: HashMap<String, Vec<String>> = HashMap::new();
-    sqlx::query!("select tag, article from tag")
-        .fetch_all(crate::db::get())
-        .await?
-        .into_iter()
-        .for_each(|row| tags.entry(row.article).or_default().push(row.tag));
+    sqlx::query!(
+        "select tag, article from tag where article in (select value from json_each(?))",
+        slugs
+    )
+    .fetch_all(crate::db::get())
+    .await?
+    .into_iter()
+    .for_each(|row| tags.entry(row.article).or_default().push(row.tag));
 
-    let fav_count: HashMap<_, _> =
-        sqlx::query!("select article, count(*) as count from favorite group by article")
-            .fetch_all(crate::db::get())
-            .await?
-            .into_iter()
-            .map(|row| (row.article, row.count))
-            .collect();
+    let fav_count: HashMap<_, _> = sqlx::query!(
+        "
+        select article, count(*) as count from favorite
+        where article in (select value from json_each(?))
+        group by article
+        ",
+        slugs
+    )
+    .fetch_all(crate::db::get())
+    .await?
+    .into_iter()
+    .map(|row| (row.article, row.count))
+    .collect();
+
+    let comment_count: HashMap<_, _> = sqlx::query!(
+        "
+        select article, count(*) as count from comment
+        where not deleted and article in (select value from json_each(?))
+        group by article
+        ",
+        slugs
+    )
+    .fetch_all(crate::db::get())
+    .await?
+    .into_iter()
+    .map(|row| (row.article, row.count))
+    .collect();
 
     let favorited: HashSet<String> = if let Some(user) = &options.user {
-        sqlx::query_scalar!("select article from favorite where user = ?", user)
-            .fetch_all(crate::db::get())
-            .await?
-            .into_iter()
-            .collect()
+        sqlx::query_scalar!(
+            "
+            select article from favorite
+            where user = ? and article in (select value from json_each(?))
+            ",
+            user,
+            slugs
+        )
+        .fetch_all(crate::db::get())
+        .await?
+        .into_iter()
+        .collect()
     } else {
         HashSet::new()
     };
 
+    // Bookmarked articles, and whether they are read
+    let bookmarks: HashMap<String, bool> = if let Some(user) = &options.user {
+        sqlx::query!(
+            r#"
+            select article, read_at is not null as "read!: bool" from bookmark
+            where user = ? and article in (select value from json_each(?))
+            "#,
+            user,
+            slugs
+        )
+        .fetch_all(crate::db::get())
+        .await?
+        .into_iter()
+        .map(|row| (row.article, row.read))
+        .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let mut reactions = Reactions::for_articles(&slugs, options.user.as_deref()).await?;
+
     let following: HashSet<String> = if let Some(user) = &options.user {
-        sqlx::query_scalar!("select followed from follow where follower = ?", user)
-            .fetch_all(crate::db::get())
-            .await?
-            .into_iter()
-            .collect()
+        sqlx::query_scalar!(
+            "
+            select followed from follow
+            where follower = ? and followed in (select value from json_each(?))
+            ",
+            user,
+            authors
+        )
+        .fetch_all(crate::db::get())
+        .await?
+        .into_iter()
+        .collect()
     } else {
         HashSet::new()
     };
@@ -169,13 +573,27 @@ async fn fill_feed_details(
             if let Some(n) = fav_count.get(slug) {
                 article.favorites_count = *n as u32;
             }
+            if let Some(n) = comment_count.get(slug) {
+                article.comments_count = *n as u32;
+            }
             article.favorited = favorited.contains(slug);
+            if let Some(read) = bookmarks.get(slug) {
+                article.bookmarked = true;
+                article.bookmark_read = *read;
+            }
+            if let Some(reactions) = reactions.remove(slug) {
+                article.reactions = reactions;
+            }
             article.author.following = following.contains(&article.author.username);
             article
         })
         .collect();
 
-    Ok(Feed { articles, count })
+    Ok(Feed {
+        articles,
+        count,
+        ..Default::default()
+    })
 }
 
 #[cfg(feature = "ssr")]
@@ -184,7 +602,10 @@ impl Article {
         let mut article = sqlx::query_as!(
             ArticleRow,
             "
-            select article.*, user.bio, user.image
+            select
+                article.slug, article.title, article.description, article.body,
+                article.created_at, article.updated_at, article.author,
+                user.bio, user.image
             from article join user on article.author = user.username
             where article.slug = ?
             ",
@@ -206,6 +627,21 @@ impl Article {
                 .await?
                 .unwrap_or_default() as u32;
 
+        article.comments_count = sqlx::query_scalar!(
+            "select count(*) from comment where article = ? and not deleted",
+            slug
+        )
+        .fetch_one(crate::db::get())
+        .await? as u32;
+
+        let slugs = serde_json::to_string(&[slug]).expect("slug as json");
+        if let Some(reactions) = Reactions::for_articles(&slugs, for_user)
+            .await?
+            .remove(slug)
+        {
+            article.reactions = reactions;
+        }
+
         if let Some(user) = for_user {
             article.favorited = sqlx::query_scalar!(
                 "select article from favorite where article = ? and user = ?",
@@ -215,6 +651,15 @@ impl Article {
             .fetch_optional(crate::db::get())
             .await?
             .is_some();
+            let read = sqlx::query_scalar!(
+                r#"select read_at is not null as "read!: bool" from bookmark where article = ? and user = ?"#,
+                slug,
+                user
+            )
+            .fetch_optional(crate::db::get())
+            .await?;
+            article.bookmarked = read.is_some();
+            article.bookmark_read = read.unwrap_or_default();
             let author = &article.author.username;
             article.author.following = sqlx::query_scalar!(
                 "select followed from follow where followed = ? and follower = ?",
@@ -231,7 +676,7 @@ impl Article {
 
     pub async fn for_editing(slug: &str, author: &str) -> Result<ArticleEditFields, sqlx::Error> {
         let article = sqlx::query!(
-            "select title, description, body from article where slug = ? and author = ?",
+            "select title, description, body, revision from article where slug = ? and author = ?",
             slug,
             author,
         )
@@ -247,26 +692,170 @@ impl Article {
             description: article.description,
             body: article.body,
             tags,
+            revision: article.revision,
         })
     }
 
     fn slug_from_title(title: &str) -> String {
-        let mut slug: String = title
-            .chars()
-            .filter_map(|c| match c {
-                ' ' => Some('-'),
-                c if c.is_ascii_alphanumeric() => Some(c),
-                _ => None,
-            })
-            .skip_while(|c| *c == '-')
-            .collect();
-        slug.make_ascii_lowercase();
-        if slug.ends_with('-') {
-            slug.push('x');
+        let mut slug = String::new();
+        for c in deunicode::deunicode(title).chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c.to_ascii_lowercase());
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        if slug.len() > MAX_SLUG_LEN {
+            // Prefer cutting at a word boundary, if there's one reasonably close
+            slug.truncate(MAX_SLUG_LEN);
+            if let Some(i) = slug.rfind('-').filter(|i| *i > MAX_SLUG_LEN / 2) {
+                slug.truncate(i);
+            }
+        }
+        while slug.ends_with('-') {
+            slug.pop();
+        }
+        if slug.is_empty() {
+            // e.g. title with only symbols or emojis
+            slug.push_str("article");
         }
         slug
     }
 
+    /// Whether `slug` is what the title would produce, or one that
+    /// [`Article::unique_slug`] would have suffixed as the base slug is taken.
+    fn slug_matches_title(slug: &str, title: &str, base_taken: bool) -> bool {
+        let base = Self::slug_from_title(title);
+        slug == base
+            || base_taken
+                && slug
+                    .strip_prefix(&base)
+                    .and_then(|rest| rest.strip_prefix('-'))
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .is_some_and(|n| n >= 2)
+    }
+
+    /// Whether the base slug of the title is taken by another article, or reserved
+    async fn base_slug_taken(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        title: &str,
+        renaming: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let base = Self::slug_from_title(title);
+        if RESERVED_SLUGS.contains(&base.as_str()) {
+            return Ok(true);
+        }
+        let taken = sqlx::query_scalar!(
+            "
+            select exists(select 1 from article where slug = ? and slug != ?)
+                or exists(select 1 from article_slug_history where slug = ? and article != ?)
+            ",
+            base,
+            renaming,
+            base,
+            renaming,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        Ok(taken != 0)
+    }
+
+    /// Slug from the title, which is not yet taken by any article nor reserved.
+    ///
+    /// Previous slugs are also considered taken, so old links keep working.
+    /// Except for the ones of the article being renamed, if any.
+    async fn unique_slug<'c>(
+        db: impl sqlx::sqlite::SqliteExecutor<'c>,
+        title: &str,
+        renaming: Option<&str>,
+    ) -> Result<String, sqlx::Error> {
+        use std::collections::HashSet;
+
+        let base = Self::slug_from_title(title);
+        // Base slug only contains [a-z0-9-], so no escaping needed for the pattern
+        let suffixed = format!("{}-%", base);
+        let taken: HashSet<String> = sqlx::query_scalar!(
+            "
+            select slug from article where slug = ? or slug like ?
+            union
+            select slug from article_slug_history
+            where (slug = ? or slug like ?) and article is not ?
+            ",
+            base,
+            suffixed,
+            base,
+            suffixed,
+            renaming,
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .collect();
+
+        Ok(Self::free_slug(&base, &taken))
+    }
+
+    /// The base slug, or the first suffixed one, that isn't taken nor reserved
+    fn free_slug(base: &str, taken: &std::collections::HashSet<String>) -> String {
+        std::iter::once(base.to_owned())
+            .chain((2..).map(|n| format!("{}-{}", base, n)))
+            .find(|slug| !taken.contains(slug) && !RESERVED_SLUGS.contains(&slug.as_str()))
+            .expect("some suffix is free")
+    }
+
+    /// Current slug of an article, which used to have the given slug.
+    pub async fn renamed_slug(old: &str) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar!(
+            "select article from article_slug_history where slug = ?",
+            old
+        )
+        .fetch_optional(crate::db::get())
+        .await
+    }
+
+    async fn rename(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        old: &str,
+        new: &str,
+    ) -> Result<(), sqlx::Error> {
+        // The article may be getting one of its previous slugs back
+        sqlx::query!("delete from article_slug_history where slug = ?", new)
+            .execute(&mut **tx)
+            .await?;
+        // Tags, comments etc. (and the history) follow through `on update cascade`
+        sqlx::query!("update article set slug = ? where slug = ?", new, old)
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query!(
+            "insert into article_slug_history (slug, article) values (?, ?)",
+            old,
+            new
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// Renames the article to a free slug from the title. Returns the new slug.
+    async fn rename_for_title(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        old: &str,
+        title: &str,
+    ) -> Result<String, sqlx::Error> {
+        // Like when creating, another article may take the slug in between
+        let mut attempts = 0;
+        loop {
+            let new = Self::unique_slug(&mut **tx, title, Some(old)).await?;
+            match Self::rename(tx, old, &new).await {
+                Ok(()) => return Ok(new),
+                Err(sqlx::Error::Database(e)) if e.is_unique_violation() && attempts < 3 => {
+                    attempts += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
     fn validate(title: &str, description: &str, body: &str, tags: &[&str]) -> Option<Vec<String>> {
         let mut errors = Vec::new();
         if title.is_empty() {
@@ -287,12 +876,7 @@ impl Article {
             errors.push("too long body");
         }
 
-        if tags.iter().any(|tag| {
-            tag.len() > 20
-                || tag
-                    .split('-')
-                    .any(|part| part.is_empty() || part.chars().any(|c| !c.is_ascii_lowercase()))
-        }) {
+        if !tags.iter().all(|tag| Self::valid_tag(tag)) {
             errors.push("invalid tag (must be short, lowercase a-z and in kebab-case)");
         }
 
@@ -303,6 +887,14 @@ impl Article {
         }
     }
 
+    /// Tags must be short, lowercase a-z and in kebab-case
+    pub fn valid_tag(tag: &str) -> bool {
+        tag.len() <= 20
+            && tag
+                .split('-')
+                .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_lowercase()))
+    }
+
     // TODO: the validation errors passed in nested Results is bit weird, but will do for now
     pub async fn create(
         author: &str,
@@ -315,58 +907,98 @@ impl Article {
             return Ok(Err(errors));
         }
 
-        let slug = Self::slug_from_title(title);
-
-        sqlx::query!(
-            "insert into article (slug, title, description, body, author) values (?, ?, ?, ?, ?)",
-            slug,
-            title,
-            description,
-            body,
-            author
-        )
-        .execute(crate::db::get())
-        .await?;
+        // Another article with the same title may be created concurrently,
+        // so retry a few times before giving up.
+        let mut attempts = 0;
+        let mut tx = crate::db::get().begin().await?;
+        let slug = loop {
+            let slug = Self::unique_slug(&mut *tx, title, None).await?;
+            let res = sqlx::query!(
+                "insert into article (slug, title, description, body, author) values (?, ?, ?, ?, ?)",
+                slug,
+                title,
+                description,
+                body,
+                author
+            )
+            .execute(&mut *tx)
+            .await;
+            match res {
+                Ok(_) => break slug,
+                Err(sqlx::Error::Database(e)) if e.is_unique_violation() && attempts < 3 => {
+                    attempts += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        Self::add_tags(&mut tx, &slug, tags).await?;
+        tx.commit().await?;
 
-        Self::add_tags(&slug, tags).await?;
+        crate::models::mention::record_article(&slug, author, body).await;
 
         Ok(Ok(slug))
     }
 
+    /// Update the article, if it is still at the given `revision`.
+    ///
+    /// With `regenerate_slug` the slug is changed to match a changed title.
+    /// Returns the resulting slug. The revision, tags and slug are changed
+    /// together, or not at all.
+    #[allow(clippy::too_many_arguments)]
     pub async fn update(
         author: &str,
         slug: &str,
+        revision: i64,
         title: &str,
         description: &str,
         body: &str,
         tags: &[&str],
-    ) -> Result<Option<Vec<String>>, sqlx::Error> {
+        regenerate_slug: bool,
+    ) -> Result<Result<String, EditRejection>, sqlx::Error> {
         if let Some(errors) = Self::validate(title, description, body, tags) {
-            return Ok(Some(errors));
+            return Ok(Err(EditRejection::Invalid(errors)));
         }
 
+        let mut tx = crate::db::get().begin().await?;
         let res = sqlx::query!(
             "
-                update article set title = ?, description = ?, body = ?, updated_at = (datetime('now'))
-                where slug = ? and author = ?
+                update article set
+                    title = ?, description = ?, body = ?,
+                    updated_at = (datetime('now')), revision = revision + 1
+                where slug = ? and author = ? and revision = ?
             ",
             title,
             description,
             body,
             slug,
             author,
+            revision,
         )
-        .execute(crate::db::get())
+        .execute(&mut *tx)
         .await?;
 
         if res.rows_affected() != 1 {
-            return Err(sqlx::Error::RowNotFound);
+            drop(tx);
+            // Either the article is gone, or it was updated in between.
+            // In the latter case give the stored version back for merging.
+            let current = Self::for_editing(slug, author).await?;
+            return Ok(Err(EditRejection::Conflict(current)));
         }
 
-        Self::clear_tags(slug).await?;
-        Self::add_tags(slug, tags).await?;
+        Self::clear_tags(&mut tx, slug).await?;
+        Self::add_tags(&mut tx, slug, tags).await?;
 
-        Ok(None)
+        let base_taken = regenerate_slug && Self::base_slug_taken(&mut tx, title, slug).await?;
+        let slug = if regenerate_slug && !Self::slug_matches_title(slug, title, base_taken) {
+            Self::rename_for_title(&mut tx, slug, title).await?
+        } else {
+            slug.to_owned()
+        };
+        tx.commit().await?;
+
+        crate::models::mention::record_article(&slug, author, body).await;
+
+        Ok(Ok(slug))
     }
 
     pub async fn delete(slug: &str) -> Result<(), sqlx::Error> {
@@ -379,18 +1011,34 @@ impl Article {
         Ok(())
     }
 
-    async fn add_tags(slug: &str, tags: &[&str]) -> Result<(), sqlx::Error> {
+    /// Adds the tags, with the aliases replaced by their tags
+    async fn add_tags(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        slug: &str,
+        tags: &[&str],
+    ) -> Result<(), sqlx::Error> {
         for tag in tags {
-            sqlx::query!("insert into tag (article, tag) values (?, ?)", slug, tag)
-                .execute(crate::db::get())
-                .await?;
+            sqlx::query!(
+                "
+                insert or ignore into tag (article, tag)
+                values (?, coalesce((select alias_of from tag_meta where tag = ?), ?))
+                ",
+                slug,
+                tag,
+                tag
+            )
+            .execute(&mut **tx)
+            .await?;
         }
         Ok(())
     }
 
-    async fn clear_tags(slug: &str) -> Result<(), sqlx::Error> {
+    async fn clear_tags(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        slug: &str,
+    ) -> Result<(), sqlx::Error> {
         sqlx::query!("delete from tag where article = ?", slug)
-            .execute(crate::db::get())
+            .execute(&mut **tx)
             .await?;
         Ok(())
     }
@@ -398,35 +1046,228 @@ impl Article {
 
 #[cfg(feature = "ssr")]
 impl Feed {
+    /// Articles matching both the `filter` and the one in `options`, newest
+    /// first unless sorted otherwise in `options`.
+    ///
+    /// Paged by the position of the articles, so that new articles don't
+    /// shift the pages, and deep pages are as fast as the first one. The
+    /// reaction counts change all the time, so that order is paged by offset,
+    /// along the index of the counts kept on the articles.
+    pub async fn filtered(filter: &FeedFilter, options: &FeedOptions) -> Result<Self, sqlx::Error> {
+        use sqlx::QueryBuilder;
+
+        let tables = "from article join user on article.author = user.username where true";
+
+        let mut count = QueryBuilder::new("select count(*) ");
+        count.push(tables);
+        filter.push_conditions(&mut count);
+        options.filter.push_conditions(&mut count);
+        options.push_muted_condition(&mut count, filter);
+        let count: i64 = count
+            .build_query_scalar()
+            .fetch_one(crate::db::get())
+            .await?;
+
+        if options.sort == FeedSort::MostReacted {
+            let offset = FeedPosition::page_offset(options.cursor.as_ref(), options.limit);
+            let mut select = QueryBuilder::new("select article.*, user.bio, user.image ");
+            select.push(tables);
+            filter.push_conditions(&mut select);
+            options.filter.push_conditions(&mut select);
+            options.push_muted_condition(&mut select, filter);
+            select
+                .push(
+                    " order by article.reaction_count desc, article.created_at desc, article.slug desc",
+                )
+                .push(" limit ")
+                .push_bind(options.limit)
+                .push(" offset ")
+                .push_bind(offset);
+            let articles = select
+                .build_query_as::<ArticleRow>()
+                .fetch_all(crate::db::get())
+                .await?;
+
+            let mut feed = fill_feed_details(articles, count as u32, options).await?;
+            let next = offset + u32::from(options.limit);
+            feed.prev = (offset > 0).then(|| FeedPosition::Offset(offset).token());
+            feed.next = (next < count as u32).then(|| FeedPosition::Offset(next).token());
+            return Ok(feed);
+        }
+
+        let (backwards, position) = match &options.cursor {
+            Some(FeedCursor::After(token)) => (false, FeedPosition::from_token(token)),
+            Some(FeedCursor::Before(token)) => (true, FeedPosition::from_token(token)),
+            None => (false, None),
+        };
+        // A token that isn't a position of an article is ignored, giving the first page
+        let position = position.filter(|p| matches!(p, FeedPosition::Article { .. }));
+        let backwards = backwards && position.is_some();
+
+        let mut select = QueryBuilder::new("select article.*, user.bio, user.image ");
+        select.push(tables);
+        filter.push_conditions(&mut select);
+        options.filter.push_conditions(&mut select);
+        options.push_muted_condition(&mut select, filter);
+        if let Some(FeedPosition::Article { created_at, slug }) = &position {
+            select
+                .push(if backwards {
+                    " and (article.created_at, article.slug) > ("
+                } else {
+                    " and (article.created_at, article.slug) < ("
+                })
+                .push_bind(created_at.clone())
+                .push(", ")
+                .push_bind(slug.clone())
+                .push(")");
+        }
+        select
+            .push(if backwards {
+                " order by article.created_at asc, article.slug asc"
+            } else {
+                " order by article.created_at desc, article.slug desc"
+            })
+            // One extra to know if there's more
+            .push(" limit ")
+            .push_bind(u32::from(options.limit) + 1);
+        let mut articles = select
+            .build_query_as::<ArticleRow>()
+            .fetch_all(crate::db::get())
+            .await?;
+        let more = articles.len() > usize::from(options.limit);
+        articles.truncate(options.limit.into());
+        if backwards {
+            articles.reverse();
+        }
+
+        let mut feed = fill_feed_details(articles, count as u32, options).await?;
+        let first = feed.articles.first().map(|a| FeedPosition::of(a).token());
+        let last = feed.articles.last().map(|a| FeedPosition::of(a).token());
+        if backwards {
+            (feed.prev, feed.next) = (first.filter(|_| more), last);
+        } else {
+            (feed.prev, feed.next) = (first.filter(|_| position.is_some()), last.filter(|_| more));
+        }
+        Ok(feed)
+    }
+
+    /// Articles of the users and tags the user follows, with the reasons.
+    ///
+    /// The user should be the one in `options`, for whom the follows are filled.
     pub async fn feed(user: &str, options: &FeedOptions) -> Result<Self, sqlx::Error> {
-        feed_query!(
-            "where article.author in (select followed from follow where follower = ?)",
-            options,
-            user
-        )
+        let mut feed = Self::filtered(&FeedFilter::default().followed_by(user), options).await?;
+        let tags = super::tag::TagFollow::for_user(user).await?;
+        for article in &feed.articles {
+            let reason = if article.author.following {
+                Some(FeedReason::Author(article.author.username.clone()))
+            } else {
+                tags.iter()
+                    .find(|tag| article.tags.contains(tag))
+                    .map(|tag| FeedReason::Tag(tag.clone()))
+            };
+            if let Some(reason) = reason {
+                feed.reasons.insert(article.slug.clone(), reason);
+            }
+        }
+        Ok(feed)
     }
 
     pub async fn global(options: &FeedOptions) -> Result<Self, sqlx::Error> {
-        feed_query!("", options,)
+        Self::filtered(&FeedFilter::default(), options).await
     }
 
     pub async fn by(user: &str, options: &FeedOptions) -> Result<Self, sqlx::Error> {
-        feed_query!("where article.author = ?", options, user)
+        Self::filtered(&FeedFilter::default().author(user), options).await
     }
 
     pub async fn favorited(user: &str, options: &FeedOptions) -> Result<Self, sqlx::Error> {
-        feed_query!(
-            "where article.slug in (select article from favorite where user = ?)",
-            options,
-            user
-        )
+        Self::filtered(&FeedFilter::default().favorited_by(user), options).await
+    }
+
+    /// Reading list of the user
+    pub async fn bookmarks(user: &str, options: &FeedOptions) -> Result<Self, sqlx::Error> {
+        Self::filtered(&FeedFilter::default().bookmarked_by(user), options).await
     }
 
     pub async fn tag(tag: &str, options: &FeedOptions) -> Result<Self, sqlx::Error> {
-        feed_query!(
-            "where article.slug in (select article from tag where tag = ?)",
-            options,
-            tag
-        )
+        Self::filtered(&FeedFilter::default().tag(tag), options).await
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use std::collections::HashSet;
+
+    use proptest::prelude::*;
+
+    use super::{Article, MAX_SLUG_LEN, RESERVED_SLUGS};
+
+    fn assert_slug_shape(slug: &str) {
+        assert!(!slug.is_empty());
+        assert!(slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'));
+        assert!(!slug.starts_with('-'), "leading dash: {:?}", slug);
+        assert!(!slug.ends_with('-'), "trailing dash: {:?}", slug);
+        assert!(!slug.contains("--"), "doubled dash: {:?}", slug);
+    }
+
+    proptest! {
+        #[test]
+        fn slug_from_title_has_slug_shape(title in "\\PC*") {
+            let slug = Article::slug_from_title(&title);
+            prop_assert!(slug.len() <= MAX_SLUG_LEN);
+            assert_slug_shape(&slug);
+        }
+
+        #[test]
+        fn slug_from_title_is_deterministic(title in "\\PC*") {
+            prop_assert_eq!(Article::slug_from_title(&title), Article::slug_from_title(&title));
+        }
+
+        #[test]
+        fn slug_from_title_matches_title(title in "\\PC*") {
+            let slug = Article::slug_from_title(&title);
+            prop_assert!(Article::slug_matches_title(&slug, &title, false));
+        }
+
+        #[test]
+        fn free_slug_is_never_taken_nor_reserved(
+            title in prop_oneof![
+                "\\PC*",
+                prop::sample::select(RESERVED_SLUGS).prop_map(str::to_owned),
+            ],
+            suffixes in prop::collection::vec(2u32..20, 0..10),
+        ) {
+            let base = Article::slug_from_title(&title);
+            let taken: HashSet<String> = std::iter::once(base.clone())
+                .chain(suffixes.iter().map(|n| format!("{}-{}", base, n)))
+                .collect();
+            let slug = Article::free_slug(&base, &taken);
+            prop_assert!(!taken.contains(&slug));
+            prop_assert!(!RESERVED_SLUGS.contains(&slug.as_str()));
+            assert_slug_shape(&slug);
+            prop_assert!(Article::slug_matches_title(&slug, &title, true));
+        }
+    }
+
+    #[test]
+    fn numbers_in_titles_are_not_suffixes() {
+        assert!(Article::slug_matches_title("top-10", "Top 10", false));
+        assert!(!Article::slug_matches_title("top-10", "Top", false));
+        assert!(Article::slug_matches_title("top-10", "Top", true));
+        // Suffixes start from 2
+        assert!(!Article::slug_matches_title("top-1", "Top", true));
+    }
+
+    #[test]
+    fn reserved_titles_get_a_suffix() {
+        for reserved in RESERVED_SLUGS {
+            let base = Article::slug_from_title(reserved);
+            assert_eq!(
+                Article::free_slug(&base, &HashSet::new()),
+                format!("{}-2", reserved)
+            );
+        }
     }
 }
@@ -0,0 +1,44 @@
+//! Private reading list of the user
+
+#[cfg(feature = "ssr")]
+pub struct Bookmark;
+
+#[cfg(feature = "ssr")]
+impl Bookmark {
+    /// Adds the article to the reading list, or removes it. Returns whether
+    /// it changed.
+    pub async fn set(user: &str, article: &str, bookmarked: bool) -> Result<bool, sqlx::Error> {
+        let res = if bookmarked {
+            sqlx::query!(
+                "insert or ignore into bookmark (user, article) values (?, ?)",
+                user,
+                article
+            )
+        } else {
+            sqlx::query!(
+                "delete from bookmark where user = ? and article = ?",
+                user,
+                article
+            )
+        }
+        .execute(crate::db::get())
+        .await?;
+        Ok(res.rows_affected() == 1)
+    }
+
+    /// Marks the bookmarked article read, or unread again
+    pub async fn mark_read(user: &str, article: &str, read: bool) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "
+            update bookmark set read_at = case when ? then coalesce(read_at, datetime('now')) end
+            where user = ? and article = ?
+            ",
+            read,
+            user,
+            article
+        )
+        .execute(crate::db::get())
+        .await?;
+        Ok(())
+    }
+}
@@ -1,75 +1,701 @@
 use serde::{Deserialize, Serialize};
 
-use super::user::Profile;
+use super::{reaction::Reactions, user::Profile};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Comment {
     pub id: i64,
     pub body: String,
+    /// Body rendered from markdown, safe to insert as is
+    pub body_html: String,
     pub created_at: String,
     pub author: Profile,
+    pub parent_id: Option<i64>,
+    /// Deleted comment kept as a placeholder for its replies, without a body or author
+    pub deleted: bool,
+    /// By a user the viewer has muted, shown as a placeholder for its replies
+    /// without a body or author
+    pub hidden: bool,
+    pub edited_at: Option<String>,
+    /// The edit window is still open
+    pub editable: bool,
+    pub reactions: Reactions,
+    pub replies: Vec<Comment>,
+}
+
+/// Order of the threads. The replies in them are always from the oldest.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CommentSort {
+    #[default]
+    Oldest,
+    Newest,
+    MostReacted,
+}
+
+impl CommentSort {
+    pub fn from_param(param: &str) -> Self {
+        match param {
+            "newest" => Self::Newest,
+            "reacted" => Self::MostReacted,
+            _ => Self::Oldest,
+        }
+    }
+
+    pub fn param(self) -> &'static str {
+        match self {
+            Self::Oldest => "oldest",
+            Self::Newest => "newest",
+            Self::MostReacted => "reacted",
+        }
+    }
+}
+
+/// Page of the threads of an article
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CommentPage {
+    pub comments: Vec<Comment>,
+    /// Comments of the article that aren't deleted nor by users the viewer
+    /// has muted, replies included
+    pub count: i64,
+    /// Cursor for the next page, if there is one
+    pub next: Option<String>,
+}
+
+/// Change to the comments of an article, for updating them live
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", content = "comment", rename_all = "snake_case")]
+pub enum CommentEvent {
+    Created(Comment),
+    /// Edited, or turned into a placeholder
+    Updated(Comment),
+    Deleted(i64),
+}
+
+fn find_mut(comments: &mut [Comment], id: i64) -> Option<&mut Comment> {
+    for comment in comments.iter_mut() {
+        if comment.id == id {
+            return Some(comment);
+        }
+        if let Some(found) = find_mut(&mut comment.replies, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+impl CommentEvent {
+    /// Applies the change to the threads, ignoring the ones already applied.
+    ///
+    /// Returns the change in the number of comments that aren't deleted, as
+    /// far as it can be told from the threads.
+    pub fn apply(&self, comments: &mut Vec<Comment>, sort: CommentSort) -> i64 {
+        match self {
+            Self::Created(comment) => {
+                if find_mut(comments, comment.id).is_some() {
+                    return 0;
+                }
+                match comment.parent_id {
+                    None if sort == CommentSort::Newest => comments.insert(0, comment.clone()),
+                    None => comments.push(comment.clone()),
+                    Some(parent) => {
+                        if let Some(parent) = find_mut(comments, parent) {
+                            parent.replies.push(comment.clone());
+                        }
+                    }
+                }
+                comment.counted().into()
+            }
+            Self::Updated(comment) => {
+                let Some(existing) = find_mut(comments, comment.id) else {
+                    return 0;
+                };
+                let change = i64::from(comment.counted()) - i64::from(existing.counted());
+                // The reactions of the event aren't for the viewer
+                let replies = std::mem::take(&mut existing.replies);
+                let reactions = std::mem::take(&mut existing.reactions);
+                *existing = Comment {
+                    replies,
+                    reactions,
+                    ..comment.clone()
+                };
+                change
+            }
+            Self::Deleted(id) => {
+                let removed = comments
+                    .iter()
+                    .position(|comment| comment.id == *id)
+                    .map(|i| comments.remove(i));
+                match removed {
+                    Some(comment) => -i64::from(comment.counted()),
+                    None => comments
+                        .iter_mut()
+                        .map(|comment| self.apply(&mut comment.replies, sort))
+                        .sum(),
+                }
+            }
+        }
+    }
+}
+
+/// Previous version of an edited comment
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CommentRevision {
+    pub body: String,
+    pub written_at: String,
 }
 
-#[cfg(feature = "ssr")]
 impl Comment {
-    pub async fn for_article(slug: &str) -> Result<Vec<Self>, sqlx::Error> {
-        sqlx::query!(
-            "
-            select comment.*, user.image
-            from comment
-            join user on comment.user = user.username
-            where comment.article = ?
-            order by comment.created_at
-            ",
-            slug
-        )
-        .map(|row| Self {
+    /// Number of the replies in the subthread
+    pub fn reply_count(&self) -> usize {
+        self.replies.iter().map(|r| 1 + r.reply_count()).sum()
+    }
+
+    /// Whether the comment is in the count, i.e. isn't a placeholder
+    fn counted(&self) -> bool {
+        !self.deleted && !self.hidden
+    }
+}
+
+/// Maximum depth of threads, configured with `COMMENT_MAX_DEPTH` at build time.
+/// Replies deeper than this are added to the deepest allowed level instead.
+#[cfg(feature = "ssr")]
+fn max_depth() -> usize {
+    std::option_env!("COMMENT_MAX_DEPTH")
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or(5)
+}
+
+/// Time in minutes after posting that comments can be edited, configured
+/// with `COMMENT_EDIT_MINUTES` at build time
+#[cfg(feature = "ssr")]
+fn edit_window() -> String {
+    let minutes: u32 = std::option_env!("COMMENT_EDIT_MINUTES")
+        .and_then(|minutes| minutes.parse().ok())
+        .unwrap_or(60);
+    // As an SQLite date modifier
+    format!("-{} minutes", minutes)
+}
+
+/// Position in the threads of an article, like `FeedPosition` for articles
+#[cfg(feature = "ssr")]
+enum CommentPosition {
+    /// Thread in chronological order
+    Thread { created_at: String, id: i64 },
+    /// Index in some other order
+    Offset(u32),
+}
+
+#[cfg(feature = "ssr")]
+impl CommentPosition {
+    const SEPARATOR: char = '\u{1f}';
+
+    fn token(&self) -> String {
+        let raw = match self {
+            Self::Thread { created_at, id } => {
+                format!("t{sep}{created_at}{sep}{id}", sep = Self::SEPARATOR)
+            }
+            Self::Offset(offset) => format!("o{sep}{offset}", sep = Self::SEPARATOR),
+        };
+        super::article::encode_token(&raw)
+    }
+
+    fn from_token(token: &str) -> Option<Self> {
+        let raw = super::article::decode_token(token)?;
+        let mut parts = raw.split(Self::SEPARATOR);
+        match (parts.next()?, parts.next()?, parts.next()) {
+            ("t", created_at, Some(id)) => Some(Self::Thread {
+                created_at: created_at.to_owned(),
+                id: id.parse().ok()?,
+            }),
+            ("o", offset, None) => offset.parse().ok().map(Self::Offset),
+            _ => None,
+        }
+    }
+}
+
+/// Nests the comments under their parents, keeping the order of the replies
+#[cfg(feature = "ssr")]
+fn into_tree(comments: Vec<Comment>) -> Vec<Comment> {
+    use std::collections::HashMap;
+
+    fn attach(
+        parent: Option<i64>,
+        children: &mut HashMap<Option<i64>, Vec<Comment>>,
+    ) -> Vec<Comment> {
+        let mut level = children.remove(&parent).unwrap_or_default();
+        for comment in &mut level {
+            comment.replies = attach(Some(comment.id), children);
+        }
+        level
+    }
+
+    let mut children = HashMap::<_, Vec<_>>::new();
+    for comment in comments {
+        children.entry(comment.parent_id).or_default().push(comment);
+    }
+    attach(None, &mut children)
+}
+
+/// Comment as stored, with its article
+#[cfg(feature = "ssr")]
+struct CommentRow {
+    id: i64,
+    body: String,
+    created_at: String,
+    article: String,
+    user: String,
+    parent_id: Option<i64>,
+    deleted: bool,
+    edited_at: Option<String>,
+    image: Option<String>,
+    editable: bool,
+}
+
+#[cfg(feature = "ssr")]
+impl From<CommentRow> for (String, Comment) {
+    fn from(row: CommentRow) -> Self {
+        let comment = Comment {
             id: row.id,
-            body: row.body,
+            // Rendered once the mentioned users are known
+            body_html: String::new(),
+            body: if row.deleted { String::new() } else { row.body },
             created_at: row.created_at,
             author: Profile {
-                username: row.user,
-                image: row.image,
+                username: if row.deleted { String::new() } else { row.user },
+                image: if row.deleted { None } else { row.image },
                 // TODO: fetch if needed on the frontend
                 bio: None,
                 following: false,
+                ..Default::default()
             },
+            parent_id: row.parent_id,
+            deleted: row.deleted,
+            hidden: false,
+            edited_at: row.edited_at,
+            editable: row.editable && !row.deleted,
+            reactions: Reactions::default(),
+            replies: Vec::new(),
+        };
+        (row.article, comment)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl Comment {
+    /// Comments in the threads of the roots, which are given as a JSON array
+    /// of IDs, with their articles
+    async fn fetch_threads(roots: &str) -> Result<Vec<(String, Self)>, sqlx::Error> {
+        let window = edit_window();
+        let rows = sqlx::query_as!(
+            CommentRow,
+            r#"
+            select comment.*, user.image,
+                comment.created_at > datetime('now', ?) as "editable!: bool"
+            from comment
+            join user on comment.user = user.username
+            where comment.id in (
+                with recursive thread(id) as (
+                    select value from json_each(?)
+                    union all
+                    select reply.id from comment as reply join thread on reply.parent_id = thread.id
+                )
+                select id from thread
+            )
+            order by comment.created_at
+            "#,
+            window,
+            roots
+        )
+        .fetch_all(crate::db::get())
+        .await?;
+        Self::render(rows).await
+    }
+
+    /// Comments with their articles, with the bodies rendered
+    async fn render(rows: Vec<CommentRow>) -> Result<Vec<(String, Self)>, sqlx::Error> {
+        let mut comments: Vec<(String, Self)> = rows.into_iter().map(Into::into).collect();
+        let users =
+            super::mention::resolve(comments.iter().map(|(_, comment)| comment.body.as_str()))
+                .await?;
+        for (_, comment) in &mut comments {
+            if !comment.deleted {
+                comment.body_html = crate::markdown::comment_html(&comment.body, &users);
+            }
+        }
+        Ok(comments)
+    }
+
+    /// Placeholder of the comment by a muted user, which keeps its place in
+    /// the thread for the replies
+    pub fn hide(&mut self) {
+        self.hidden = true;
+        self.body.clear();
+        self.body_html.clear();
+        self.author = Profile::default();
+        self.edited_at = None;
+        self.editable = false;
+        self.reactions = Reactions::default();
+    }
+
+    /// Page of the threads of the article, after the cursor from the previous page.
+    ///
+    /// The comments of the users the viewer has muted are placeholders, like
+    /// deleted ones, and aren't counted either.
+    pub async fn for_article(
+        slug: &str,
+        sort: CommentSort,
+        after: Option<&str>,
+        limit: u8,
+        viewer: Option<&str>,
+    ) -> Result<CommentPage, sqlx::Error> {
+        use std::collections::HashMap;
+
+        let position = after.and_then(CommentPosition::from_token);
+        let offset = match position {
+            Some(CommentPosition::Offset(offset)) => offset,
+            _ => 0,
+        };
+
+        let mut query = sqlx::QueryBuilder::new(
+            "select id, created_at from comment where parent_id is null and article = ",
+        );
+        query.push_bind(slug);
+        match sort {
+            CommentSort::Oldest | CommentSort::Newest => {
+                let newest = sort == CommentSort::Newest;
+                if let Some(CommentPosition::Thread { created_at, id }) = &position {
+                    query
+                        .push(if newest {
+                            " and (created_at, id) < ("
+                        } else {
+                            " and (created_at, id) > ("
+                        })
+                        .push_bind(created_at.clone())
+                        .push(", ")
+                        .push_bind(*id)
+                        .push(")");
+                }
+                query.push(if newest {
+                    " order by created_at desc, id desc"
+                } else {
+                    " order by created_at, id"
+                });
+            }
+            CommentSort::MostReacted => {
+                query.push(
+                    "
+                    order by (select count(*) from comment_reaction where comment_reaction.comment = comment.id) desc,
+                        created_at, id
+                    ",
+                );
+            }
+        }
+        query
+            .push(" limit ")
+            .push_bind(i64::from(limit) + 1)
+            .push(" offset ")
+            .push_bind(if sort == CommentSort::MostReacted {
+                offset
+            } else {
+                0
+            });
+
+        let mut roots: Vec<(i64, String)> =
+            query.build_query_as().fetch_all(crate::db::get()).await?;
+        let more = roots.len() > usize::from(limit);
+        roots.truncate(limit.into());
+
+        let ids: Vec<_> = roots.iter().map(|(id, _)| id).collect();
+        let ids = serde_json::to_string(&ids).expect("ids as json");
+        let comments = Self::fetch_threads(&ids).await?;
+        let hidden = match viewer {
+            Some(viewer) => crate::models::mute::MutedUser::hidden_from(viewer).await?,
+            None => Default::default(),
+        };
+        let all_ids: Vec<_> = comments.iter().map(|(_, c)| c.id).collect();
+        let all_ids = serde_json::to_string(&all_ids).expect("ids as json");
+        let mut reactions = Reactions::for_comments(&all_ids, viewer).await?;
+        let mut threads: HashMap<_, _> = into_tree(
+            comments
+                .into_iter()
+                .map(|(_, mut c)| {
+                    c.reactions = reactions.remove(&c.id).unwrap_or_default();
+                    if hidden.contains(&c.author.username) {
+                        c.hide();
+                    }
+                    c
+                })
+                .collect(),
+        )
+        .into_iter()
+        .map(|thread| (thread.id, thread))
+        .collect();
+
+        let count = sqlx::query_scalar!(
+            "
+            select count(*) from comment
+            where article = ? and not deleted
+                and user not in (select muted from mute where user = ?)
+            ",
+            slug,
+            viewer
+        )
+        .fetch_one(crate::db::get())
+        .await?;
+
+        let next = roots.last().filter(|_| more).map(|(id, created_at)| {
+            match sort {
+                CommentSort::MostReacted => CommentPosition::Offset(offset + u32::from(limit)),
+                _ => CommentPosition::Thread {
+                    created_at: created_at.clone(),
+                    id: *id,
+                },
+            }
+            .token()
+        });
+
+        Ok(CommentPage {
+            comments: roots
+                .iter()
+                .filter_map(|(id, _)| threads.remove(id))
+                .collect(),
+            count,
+            next,
         })
+    }
+
+    /// Comment without its replies, with the slug of its article
+    async fn get(id: i64) -> Result<(String, Self), sqlx::Error> {
+        let window = edit_window();
+        let row = sqlx::query_as!(
+            CommentRow,
+            r#"
+            select comment.*, user.image,
+                comment.created_at > datetime('now', ?) as "editable!: bool"
+            from comment
+            join user on comment.user = user.username
+            where comment.id = ?
+            "#,
+            window,
+            id
+        )
+        .fetch_optional(crate::db::get())
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+        Ok(Self::render(vec![row]).await?.remove(0))
+    }
+
+    /// Parent of a reply to the comment, which is the comment itself unless
+    /// the reply would be too deep
+    async fn reply_parent(slug: &str, comment: i64) -> Result<Option<i64>, sqlx::Error> {
+        // From the root of the thread to the comment
+        let thread = sqlx::query_scalar!(
+            r#"
+            with recursive ancestor(id, parent_id, depth) as (
+                select id, parent_id, 0 from comment where id = ? and article = ?
+                union all
+                select comment.id, comment.parent_id, ancestor.depth + 1
+                from comment join ancestor on comment.id = ancestor.parent_id
+            )
+            select id as "id!: i64" from ancestor order by depth desc
+            "#,
+            comment,
+            slug
+        )
         .fetch_all(crate::db::get())
-        .await
+        .await?;
+        if thread.is_empty() {
+            return Err(sqlx::Error::RowNotFound);
+        }
+        let depth = thread.len().min(max_depth().saturating_sub(1));
+        Ok(depth.checked_sub(1).map(|parent| thread[parent]))
     }
 
-    pub async fn create(slug: &str, user: &str, body: &str) -> Result<i64, sqlx::Error> {
+    /// Returns the created comment, as it is sent to the live updates
+    pub async fn create(
+        slug: &str,
+        user: &str,
+        body: &str,
+        parent: Option<i64>,
+    ) -> Result<Comment, sqlx::Error> {
+        let parent = match parent {
+            Some(parent) => Self::reply_parent(slug, parent).await?,
+            None => None,
+        };
         let res = sqlx::query!(
             "
-            insert into comment (article, user, body)
-            values (?, ?, ?)
+            insert into comment (article, user, body, parent_id)
+            values (?, ?, ?, ?)
             ",
             slug,
             user,
-            body
+            body,
+            parent
         )
         .execute(crate::db::get())
         .await?;
+        let id = res.last_insert_rowid();
+        crate::models::mention::record_comment(id, slug, user, body).await;
 
-        Ok(res.last_insert_rowid())
+        let (_, comment) = Self::get(id).await?;
+        crate::realtime::publish(slug, CommentEvent::Created(comment.clone()));
+        Ok(comment)
     }
 
-    pub async fn delete(id: i64, user: &str) -> Result<(), sqlx::Error> {
+    /// Replaces the body of the user's comment while the edit window is open,
+    /// keeping the previous version.
+    ///
+    /// Returns the edited comment, or nothing if the body is unchanged. Fails
+    /// with `RowNotFound` if the comment can't be edited.
+    pub async fn edit(id: i64, user: &str, body: &str) -> Result<Option<Comment>, sqlx::Error> {
+        let window = edit_window();
+        let mut tx = crate::db::get().begin().await?;
+
         let res = sqlx::query!(
             "
-            delete from comment
-            where id = ? and user = ?
+            insert into comment_revision (comment, body, written_at)
+            select id, body, coalesce(edited_at, created_at)
+            from comment
+            where id = ? and user = ? and not deleted and created_at > datetime('now', ?)
+                and body != ?
             ",
             id,
             user,
+            window,
+            body,
         )
-        .execute(crate::db::get())
+        .execute(&mut *tx)
+        .await?;
+        if res.rows_affected() == 0 {
+            // Either unchanged or not editable
+            let unchanged = sqlx::query_scalar!(
+                "select 1 from comment where id = ? and user = ? and body = ?",
+                id,
+                user,
+                body
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+            return unchanged.map(|_| None).ok_or(sqlx::Error::RowNotFound);
+        }
+        sqlx::query!(
+            "update comment set body = ?, edited_at = datetime('now') where id = ?",
+            body,
+            id
+        )
+        .execute(&mut *tx)
         .await?;
-        if res.rows_affected() == 1 {
-            Ok(())
+        tx.commit().await?;
+
+        let (article, comment) = Self::get(id).await?;
+        crate::models::mention::record_comment(id, &article, user, body).await;
+        crate::realtime::publish(&article, CommentEvent::Updated(comment.clone()));
+        Ok(Some(comment))
+    }
+
+    /// Previous versions of the comment, from the oldest
+    pub async fn history(id: i64) -> Result<Vec<CommentRevision>, sqlx::Error> {
+        sqlx::query_as!(
+            CommentRevision,
+            "
+            select body, written_at from comment_revision
+            where comment = ?
+            order by id
+            ",
+            id
+        )
+        .fetch_all(crate::db::get())
+        .await
+    }
+
+    /// Deletes the comment, or leaves a placeholder if it has replies.
+    ///
+    /// The previous versions of a placeholder, including the deleted text, are
+    /// kept for moderators, and are removed with it when its last reply is.
+    ///
+    /// Returns the changes, as they are sent to the live updates.
+    pub async fn delete(id: i64, user: &str) -> Result<Vec<CommentEvent>, sqlx::Error> {
+        let mut tx = crate::db::get().begin().await?;
+
+        let has_replies = sqlx::query_scalar!(
+            "select exists(select 1 from comment where parent_id = ?)",
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?
+            != 0;
+        let (article, mut parent) = if has_replies {
+            // The deleted text becomes the last of the previous versions
+            sqlx::query!(
+                "
+                insert into comment_revision (comment, body, written_at)
+                select id, body, coalesce(edited_at, created_at)
+                from comment
+                where id = ? and user = ? and not deleted
+                ",
+                id,
+                user,
+            )
+            .execute(&mut *tx)
+            .await?;
+            let article = sqlx::query_scalar!(
+                "
+                update comment set deleted = true, body = '', edited_at = datetime('now')
+                where id = ? and user = ? and not deleted
+                returning article
+                ",
+                id,
+                user,
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+            (article, None)
         } else {
-            Err(sqlx::Error::RowNotFound)
+            let row = sqlx::query!(
+                "
+                delete from comment
+                where id = ? and user = ? and not deleted
+                returning parent_id, article
+                ",
+                id,
+                user,
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+            (row.article, row.parent_id)
+        };
+
+        let mut deleted = if has_replies { Vec::new() } else { vec![id] };
+        while let Some(id) = parent {
+            parent = sqlx::query_scalar!(
+                "
+                delete from comment
+                where id = ? and deleted
+                    and not exists (select 1 from comment as reply where reply.parent_id = comment.id)
+                returning parent_id
+                ",
+                id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .and_then(|grandparent| {
+                deleted.push(id);
+                grandparent
+            });
+        }
+        tx.commit().await?;
+
+        let mut events = Vec::new();
+        if has_replies {
+            let (_, placeholder) = Self::get(id).await?;
+            events.push(CommentEvent::Updated(placeholder));
+        }
+        events.extend(deleted.into_iter().map(CommentEvent::Deleted));
+        for event in &events {
+            crate::realtime::publish(&article, event.clone());
         }
+        Ok(events)
     }
 }
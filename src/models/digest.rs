@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+
+/// How often the user gets an email digest
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DigestFrequency {
+    #[default]
+    Never,
+    Daily,
+    Weekly,
+}
+
+impl DigestFrequency {
+    pub const ALL: [Self; 3] = [Self::Never, Self::Daily, Self::Weekly];
+
+    /// Name of the frequency in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Never => "never",
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+        }
+    }
+
+    pub fn from_name(frequency: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.as_str() == frequency)
+    }
+
+    /// Description for the settings
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Never => "Never",
+            Self::Daily => "Daily",
+            Self::Weekly => "Weekly",
+        }
+    }
+
+    /// Number of days a digest covers
+    pub fn days(self) -> Option<u32> {
+        match self {
+            Self::Never => None,
+            Self::Daily => Some(1),
+            Self::Weekly => Some(7),
+        }
+    }
+}
+
+/// User whose digest is due
+#[cfg(feature = "ssr")]
+#[derive(Debug)]
+pub struct DigestSubscription {
+    pub user: String,
+    pub email: String,
+    pub frequency: DigestFrequency,
+    /// Token for unsubscribing
+    pub token: String,
+}
+
+#[cfg(feature = "ssr")]
+impl DigestSubscription {
+    pub async fn frequency(user: &str) -> Result<DigestFrequency, sqlx::Error> {
+        let frequency = sqlx::query_scalar!("select frequency from digest where user = ?", user)
+            .fetch_optional(crate::db::get())
+            .await?;
+        Ok(frequency
+            .as_deref()
+            .and_then(DigestFrequency::from_name)
+            .unwrap_or_default())
+    }
+
+    /// Sets the frequency, counting the first period from now
+    pub async fn set_frequency(user: &str, frequency: DigestFrequency) -> Result<(), sqlx::Error> {
+        let token = crate::models::user::random_token();
+        let frequency = frequency.as_str();
+        sqlx::query!(
+            "
+            insert into digest (user, frequency, token, sent_at) values (?, ?, ?, datetime('now'))
+            on conflict (user) do update set
+                frequency = excluded.frequency,
+                sent_at = coalesce(sent_at, excluded.sent_at)
+            ",
+            user,
+            frequency,
+            token
+        )
+        .execute(crate::db::get())
+        .await?;
+        Ok(())
+    }
+
+    /// Subscriptions whose period has passed since the previous digest.
+    ///
+    /// Digests are checked for every `slack_hours`, so they count as due that
+    /// much early, instead of drifting later every time.
+    pub async fn due(slack_hours: u32) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query!(
+            "
+            select digest.user, user.email, frequency, token
+            from digest join user on user.username = digest.user
+            where frequency != 'never'
+                and (sent_at is null or sent_at <= datetime(
+                    'now',
+                    case frequency when 'weekly' then '-7 days' else '-1 days' end,
+                    ?
+                ))
+            ",
+            format!("+{} hours", slack_hours)
+        )
+        .fetch_all(crate::db::get())
+        .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(Self {
+                    frequency: DigestFrequency::from_name(&row.frequency)?,
+                    user: row.user,
+                    email: row.email,
+                    token: row.token,
+                })
+            })
+            .collect())
+    }
+
+    pub async fn mark_sent(user: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "update digest set sent_at = datetime('now') where user = ?",
+            user
+        )
+        .execute(crate::db::get())
+        .await?;
+        Ok(())
+    }
+
+    /// Turns off the digests of the token's owner. Returns whether the token
+    /// was valid.
+    pub async fn unsubscribe(token: &str) -> Result<bool, sqlx::Error> {
+        let res = sqlx::query!(
+            "update digest set frequency = 'never' where token = ?",
+            token
+        )
+        .execute(crate::db::get())
+        .await?;
+        Ok(res.rows_affected() != 0)
+    }
+}
@@ -0,0 +1,134 @@
+//! `@username` mentions in articles and comments
+
+#[cfg(feature = "ssr")]
+use std::collections::HashSet;
+
+#[cfg(feature = "ssr")]
+use crate::models::notification::{Notification, NotificationKind};
+
+/// Notifies the newly mentioned users, without failing the write that
+/// mentioned them
+#[cfg(feature = "ssr")]
+async fn notify(
+    mentioned: Result<Vec<String>, sqlx::Error>,
+    actor: &str,
+    article: &str,
+    comment: Option<i64>,
+) {
+    let users = match mentioned {
+        Ok(users) => users,
+        Err(e) => {
+            tracing::error!("could not store mentions: {:?}", e);
+            return;
+        }
+    };
+    for user in users {
+        let res = Notification::notify(
+            &user,
+            NotificationKind::Mention,
+            actor,
+            Some(article),
+            comment,
+        )
+        .await;
+        if let Err(e) = res {
+            tracing::error!("could not notify of a mention: {:?}", e);
+        }
+    }
+}
+
+/// Stores the mentions of existing users in the article and notifies the
+/// ones not mentioned in it before. Users who blocked the author are left out.
+#[cfg(feature = "ssr")]
+pub async fn record_article(slug: &str, author: &str, body: &str) {
+    let names = serde_json::to_string(&crate::markdown::mentions(body)).unwrap_or_default();
+    let mentioned = sqlx::query_scalar!(
+        "
+        insert or ignore into article_mention (article, user)
+        select ?, username from user
+        where username in (select value from json_each(?)) and username != ?
+            and username not in (select user from mute where muted = ? and block)
+        returning user
+        ",
+        slug,
+        names,
+        author,
+        author
+    )
+    .fetch_all(crate::db::get())
+    .await;
+    notify(mentioned, author, slug, None).await;
+}
+
+/// Stores the mentions of existing users in the comment and notifies the
+/// ones not mentioned in it before. Users who blocked the author are left out.
+#[cfg(feature = "ssr")]
+pub async fn record_comment(id: i64, article: &str, author: &str, body: &str) {
+    let names = serde_json::to_string(&crate::markdown::mentions(body)).unwrap_or_default();
+    let mentioned = sqlx::query_scalar!(
+        "
+        insert or ignore into comment_mention (comment, user)
+        select ?, username from user
+        where username in (select value from json_each(?)) and username != ?
+            and username not in (select user from mute where muted = ? and block)
+        returning user
+        ",
+        id,
+        names,
+        author,
+        author
+    )
+    .fetch_all(crate::db::get())
+    .await;
+    notify(mentioned, author, article, Some(id)).await;
+}
+
+/// The users mentioned in the markdown bodies, i.e. the mentions that are of
+/// existing usernames, for linking them
+#[cfg(feature = "ssr")]
+pub async fn resolve<'a>(
+    bodies: impl IntoIterator<Item = &'a str>,
+) -> Result<HashSet<String>, sqlx::Error> {
+    let mut names: Vec<String> = bodies
+        .into_iter()
+        .flat_map(crate::markdown::mentions)
+        .collect();
+    if names.is_empty() {
+        return Ok(HashSet::new());
+    }
+    names.sort_unstable();
+    names.dedup();
+    let names = serde_json::to_string(&names).unwrap_or_default();
+    let users = sqlx::query_scalar!(
+        "select username from user where username in (select value from json_each(?))",
+        names
+    )
+    .fetch_all(crate::db::get())
+    .await?;
+    Ok(users.into_iter().collect())
+}
+
+/// Usernames starting with the prefix, for completing mentions
+#[cfg(feature = "ssr")]
+pub async fn suggest(prefix: &str, limit: i64) -> Result<Vec<String>, sqlx::Error> {
+    // Escape the pattern characters, so they match themselves
+    let pattern = format!(
+        "{}%",
+        prefix
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+    sqlx::query_scalar!(
+        r#"
+        select username from user
+        where username like ? escape '\'
+        order by length(username), username
+        limit ?
+        "#,
+        pattern,
+        limit
+    )
+    .fetch_all(crate::db::get())
+    .await
+}
@@ -1,3 +1,11 @@
 pub mod user;
 pub mod article;
 pub mod comment;
+pub mod search;
+pub mod notification;
+pub mod mention;
+pub mod digest;
+pub mod mute;
+pub mod bookmark;
+pub mod reaction;
+pub mod tag;
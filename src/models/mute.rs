@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use std::collections::HashSet;
+
+/// Muting hides the other user's articles and comments, and blocking
+/// also keeps them from interacting with the user
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MuteKind {
+    Mute,
+    Block,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MutedUser {
+    pub username: String,
+    pub image: Option<String>,
+    pub kind: MuteKind,
+}
+
+#[cfg(feature = "ssr")]
+impl MutedUser {
+    /// Users the user has muted or blocked, the latest first
+    pub async fn for_user(user: &str) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            select muted, image, block as "block: bool"
+            from mute join user on user.username = mute.muted
+            where user = ?
+            order by mute.created_at desc
+            "#,
+            user
+        )
+        .map(|row| Self {
+            username: row.muted,
+            image: row.image,
+            kind: if row.block {
+                MuteKind::Block
+            } else {
+                MuteKind::Mute
+            },
+        })
+        .fetch_all(crate::db::get())
+        .await
+    }
+
+    /// Whether the user has muted or blocked the other one
+    pub async fn kind(user: &str, other: &str) -> Result<Option<MuteKind>, sqlx::Error> {
+        let block = sqlx::query_scalar!(
+            r#"select block as "block: bool" from mute where user = ? and muted = ?"#,
+            user,
+            other
+        )
+        .fetch_optional(crate::db::get())
+        .await?;
+        Ok(block.map(|block| {
+            if block {
+                MuteKind::Block
+            } else {
+                MuteKind::Mute
+            }
+        }))
+    }
+
+    /// Usernames whose content is hidden from the user
+    pub async fn hidden_from(user: &str) -> Result<HashSet<String>, sqlx::Error> {
+        let muted = sqlx::query_scalar!("select muted from mute where user = ?", user)
+            .fetch_all(crate::db::get())
+            .await?;
+        Ok(muted.into_iter().collect())
+    }
+
+    /// Whether the user has blocked the other one
+    pub async fn is_blocked(by: &str, user: &str) -> Result<bool, sqlx::Error> {
+        Ok(Self::kind(by, user).await? == Some(MuteKind::Block))
+    }
+
+    /// Mutes or blocks the other user, or with `None` undoes it. Blocking
+    /// ends the follows between them in both directions.
+    pub async fn set(user: &str, other: &str, kind: Option<MuteKind>) -> Result<(), sqlx::Error> {
+        let Some(kind) = kind else {
+            sqlx::query!("delete from mute where user = ? and muted = ?", user, other)
+                .execute(crate::db::get())
+                .await?;
+            return Ok(());
+        };
+        let block = kind == MuteKind::Block;
+        let mut tx = crate::db::get().begin().await?;
+        sqlx::query!(
+            "
+            insert into mute (user, muted, block) values (?, ?, ?)
+            on conflict (user, muted) do update set block = excluded.block
+            ",
+            user,
+            other,
+            block
+        )
+        .execute(&mut *tx)
+        .await?;
+        if block {
+            sqlx::query!(
+                "
+                delete from follow
+                where (follower = ? and followed = ?) or (follower = ? and followed = ?)
+                ",
+                user,
+                other,
+                other,
+                user
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+}
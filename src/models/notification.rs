@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationKind {
+    Follow,
+    Favorite,
+    Comment,
+    Reply,
+    Mention,
+}
+
+impl NotificationKind {
+    pub const ALL: [Self; 5] = [
+        Self::Follow,
+        Self::Favorite,
+        Self::Comment,
+        Self::Reply,
+        Self::Mention,
+    ];
+
+    /// Name of the kind in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Follow => "follow",
+            Self::Favorite => "favorite",
+            Self::Comment => "comment",
+            Self::Reply => "reply",
+            Self::Mention => "mention",
+        }
+    }
+
+    pub fn from_name(kind: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == kind)
+    }
+
+    /// Description for the settings
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Follow => "Someone follows you",
+            Self::Favorite => "Someone favorites your article",
+            Self::Comment => "Someone comments on your article",
+            Self::Reply => "Someone replies to your comment",
+            Self::Mention => "Someone mentions you",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Notification {
+    pub id: i64,
+    pub kind: NotificationKind,
+    /// User whose action it was
+    pub actor: String,
+    pub article: Option<String>,
+    pub article_title: Option<String>,
+    pub comment: Option<i64>,
+    pub created_at: String,
+    pub read: bool,
+}
+
+/// Number of the latest notifications shown
+#[cfg(feature = "ssr")]
+const LATEST: i64 = 100;
+
+#[cfg(feature = "ssr")]
+impl Notification {
+    /// Notifies the user of the actor's action, unless it was their own, they
+    /// have muted the actor or turned off the kind.
+    ///
+    /// Repeats of the same follow or favorite are grouped into the existing
+    /// notification, which is made unread again.
+    pub async fn notify(
+        user: &str,
+        kind: NotificationKind,
+        actor: &str,
+        article: Option<&str>,
+        comment: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        if user == actor {
+            return Ok(());
+        }
+        // Also keeps blocked users from e.g. mentioning the user
+        let muted = sqlx::query_scalar!(
+            "select 1 from mute where user = ? and muted = ?",
+            user,
+            actor
+        )
+        .fetch_optional(crate::db::get())
+        .await?
+        .is_some();
+        if muted {
+            return Ok(());
+        }
+        let kind_name = kind.as_str();
+        let opted_out = sqlx::query_scalar!(
+            "select 1 from notification_opt_out where user = ? and kind = ?",
+            user,
+            kind_name
+        )
+        .fetch_optional(crate::db::get())
+        .await?
+        .is_some();
+        if opted_out {
+            return Ok(());
+        }
+
+        if matches!(kind, NotificationKind::Follow | NotificationKind::Favorite) {
+            let res = sqlx::query!(
+                "
+                update notification set created_at = datetime('now'), read_at = null
+                where user = ? and kind = ? and actor = ? and article is ?
+                ",
+                user,
+                kind_name,
+                actor,
+                article
+            )
+            .execute(crate::db::get())
+            .await?;
+            if res.rows_affected() != 0 {
+                return Ok(());
+            }
+        }
+        sqlx::query!(
+            "
+            insert into notification (user, kind, actor, article, comment)
+            values (?, ?, ?, ?, ?)
+            ",
+            user,
+            kind_name,
+            actor,
+            article,
+            comment
+        )
+        .execute(crate::db::get())
+        .await?;
+        Ok(())
+    }
+
+    /// Notifies the author of the article that it was favorited
+    pub async fn notify_favorite(article: &str, actor: &str) -> Result<(), sqlx::Error> {
+        let author = sqlx::query_scalar!("select author from article where slug = ?", article)
+            .fetch_one(crate::db::get())
+            .await?;
+        Self::notify(
+            &author,
+            NotificationKind::Favorite,
+            actor,
+            Some(article),
+            None,
+        )
+        .await
+    }
+
+    /// Notifies the author of the article, and of the parent if it's a reply
+    pub async fn notify_comment(comment: i64, actor: &str) -> Result<(), sqlx::Error> {
+        let row = sqlx::query!(
+            "
+            select comment.article, article.author, parent.user as parent_author
+            from comment
+            join article on article.slug = comment.article
+            left join comment as parent on parent.id = comment.parent_id and not parent.deleted
+            where comment.id = ?
+            ",
+            comment
+        )
+        .fetch_one(crate::db::get())
+        .await?;
+        let article = Some(row.article.as_str());
+        if let Some(parent_author) = &row.parent_author {
+            Self::notify(
+                parent_author,
+                NotificationKind::Reply,
+                actor,
+                article,
+                Some(comment),
+            )
+            .await?;
+        }
+        // Replying to the author already notified them
+        if row.parent_author.as_ref() != Some(&row.author) {
+            Self::notify(
+                &row.author,
+                NotificationKind::Comment,
+                actor,
+                article,
+                Some(comment),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Latest notifications of the user, the newest first
+    pub async fn for_user(user: &str) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query!(
+            "
+            select notification.id, kind, actor, article, title, comment,
+                notification.created_at, read_at
+            from notification
+            left join article on article.slug = notification.article
+            where user = ?
+            order by notification.created_at desc, notification.id desc
+            limit ?
+            ",
+            user,
+            LATEST
+        )
+        .fetch_all(crate::db::get())
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .filter_map(|row| {
+                    Some(Self {
+                        id: row.id,
+                        kind: NotificationKind::from_name(&row.kind)?,
+                        actor: row.actor,
+                        article: row.article,
+                        article_title: row.title,
+                        comment: row.comment,
+                        created_at: row.created_at,
+                        read: row.read_at.is_some(),
+                    })
+                })
+                .collect()
+        })
+    }
+
+    pub async fn unread_count(user: &str) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            "select count(*) from notification where user = ? and read_at is null",
+            user
+        )
+        .fetch_one(crate::db::get())
+        .await
+    }
+
+    /// Marks the notification, or all of them without an ID, as read
+    pub async fn mark_read(user: &str, id: Option<i64>) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "
+            update notification set read_at = datetime('now')
+            where user = ? and id = coalesce(?, id) and read_at is null
+            ",
+            user,
+            id
+        )
+        .execute(crate::db::get())
+        .await?;
+        Ok(())
+    }
+
+    /// Kinds of notifications the user has turned off
+    pub async fn opted_out(user: &str) -> Result<Vec<NotificationKind>, sqlx::Error> {
+        let kinds =
+            sqlx::query_scalar!("select kind from notification_opt_out where user = ?", user)
+                .fetch_all(crate::db::get())
+                .await?;
+        Ok(kinds
+            .iter()
+            .filter_map(|k| NotificationKind::from_name(k))
+            .collect())
+    }
+
+    pub async fn set_enabled(
+        user: &str,
+        kind: NotificationKind,
+        enabled: bool,
+    ) -> Result<(), sqlx::Error> {
+        let kind = kind.as_str();
+        if enabled {
+            sqlx::query!(
+                "delete from notification_opt_out where user = ? and kind = ?",
+                user,
+                kind
+            )
+        } else {
+            sqlx::query!(
+                "insert or ignore into notification_opt_out (user, kind) values (?, ?)",
+                user,
+                kind
+            )
+        }
+        .execute(crate::db::get())
+        .await?;
+        Ok(())
+    }
+}
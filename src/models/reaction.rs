@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use std::collections::HashMap;
+
+/// Emoji reaction to an article or a comment
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Reaction {
+    ThumbsUp,
+    Tada,
+    Heart,
+    Rocket,
+    Eyes,
+    Confused,
+}
+
+impl Reaction {
+    pub const ALL: [Self; 6] = [
+        Self::ThumbsUp,
+        Self::Tada,
+        Self::Heart,
+        Self::Rocket,
+        Self::Eyes,
+        Self::Confused,
+    ];
+
+    /// Name of the reaction in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ThumbsUp => "+1",
+            Self::Tada => "tada",
+            Self::Heart => "heart",
+            Self::Rocket => "rocket",
+            Self::Eyes => "eyes",
+            Self::Confused => "confused",
+        }
+    }
+
+    pub fn from_name(reaction: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.as_str() == reaction)
+    }
+
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Self::ThumbsUp => "\u{1f44d}",
+            Self::Tada => "\u{1f389}",
+            Self::Heart => "\u{2764}\u{fe0f}",
+            Self::Rocket => "\u{1f680}",
+            Self::Eyes => "\u{1f440}",
+            Self::Confused => "\u{1f615}",
+        }
+    }
+
+    /// Description for the buttons
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::ThumbsUp => "Thumbs up",
+            Self::Tada => "Hooray",
+            Self::Heart => "Heart",
+            Self::Rocket => "Rocket",
+            Self::Eyes => "Eyes",
+            Self::Confused => "Confused",
+        }
+    }
+}
+
+/// Reactions to an article or a comment
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reactions {
+    /// Reactions given at least once, with their counts
+    pub counts: Vec<(Reaction, u32)>,
+    /// Reactions of the viewer
+    pub own: Vec<Reaction>,
+}
+
+impl Reactions {
+    pub fn count(&self, reaction: Reaction) -> u32 {
+        self.counts
+            .iter()
+            .find(|(r, _)| *r == reaction)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    pub fn reacted(&self, reaction: Reaction) -> bool {
+        self.own.contains(&reaction)
+    }
+
+    #[cfg(feature = "ssr")]
+    fn add(&mut self, reaction: &str, count: i64, own: bool) {
+        let Some(reaction) = Reaction::from_name(reaction) else {
+            return;
+        };
+        self.counts.push((reaction, count as u32));
+        if own {
+            self.own.push(reaction);
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl Reactions {
+    /// Reactions to the articles, which are given as a JSON array of slugs
+    pub async fn for_articles(
+        slugs: &str,
+        viewer: Option<&str>,
+    ) -> Result<HashMap<String, Self>, sqlx::Error> {
+        let mut reactions = HashMap::<_, Self>::new();
+        sqlx::query!(
+            r#"
+            select article, reaction, count(*) as "count!: i64",
+                coalesce(max(user = ?), false) as "own!: bool"
+            from article_reaction
+            where article in (select value from json_each(?))
+            group by article, reaction
+            "#,
+            viewer,
+            slugs
+        )
+        .fetch_all(crate::db::get())
+        .await?
+        .into_iter()
+        .for_each(|row| {
+            reactions
+                .entry(row.article)
+                .or_default()
+                .add(&row.reaction, row.count, row.own)
+        });
+        Ok(reactions)
+    }
+
+    /// Reactions to the comments, which are given as a JSON array of IDs
+    pub async fn for_comments(
+        ids: &str,
+        viewer: Option<&str>,
+    ) -> Result<HashMap<i64, Self>, sqlx::Error> {
+        let mut reactions = HashMap::<_, Self>::new();
+        sqlx::query!(
+            r#"
+            select comment, reaction, count(*) as "count!: i64",
+                coalesce(max(user = ?), false) as "own!: bool"
+            from comment_reaction
+            where comment in (select value from json_each(?))
+            group by comment, reaction
+            "#,
+            viewer,
+            ids
+        )
+        .fetch_all(crate::db::get())
+        .await?
+        .into_iter()
+        .for_each(|row| {
+            reactions
+                .entry(row.comment)
+                .or_default()
+                .add(&row.reaction, row.count, row.own)
+        });
+        Ok(reactions)
+    }
+
+    /// Gives the reaction to the article, or takes it back. Returns whether
+    /// it changed.
+    pub async fn set_article(
+        user: &str,
+        article: &str,
+        reaction: Reaction,
+        reacted: bool,
+    ) -> Result<bool, sqlx::Error> {
+        let reaction = reaction.as_str();
+        let res = if reacted {
+            sqlx::query!(
+                "
+                insert or ignore into article_reaction (article, user, reaction)
+                select slug, ?, ? from article where slug = ?
+                ",
+                user,
+                reaction,
+                article
+            )
+        } else {
+            sqlx::query!(
+                "delete from article_reaction where article = ? and user = ? and reaction = ?",
+                article,
+                user,
+                reaction
+            )
+        }
+        .execute(crate::db::get())
+        .await?;
+        Ok(res.rows_affected() == 1)
+    }
+
+    /// Gives the reaction to the comment of the article, or takes it back.
+    /// Deleted comments can't be reacted to.
+    pub async fn set_comment(
+        user: &str,
+        article: &str,
+        comment: i64,
+        reaction: Reaction,
+        reacted: bool,
+    ) -> Result<bool, sqlx::Error> {
+        let reaction = reaction.as_str();
+        let res = if reacted {
+            sqlx::query!(
+                "
+                insert or ignore into comment_reaction (comment, user, reaction)
+                select id, ?, ? from comment where id = ? and article = ? and not deleted
+                ",
+                user,
+                reaction,
+                comment,
+                article
+            )
+        } else {
+            sqlx::query!(
+                "delete from comment_reaction where comment = ? and user = ? and reaction = ?",
+                comment,
+                user,
+                reaction
+            )
+        }
+        .execute(crate::db::get())
+        .await?;
+        Ok(res.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,248 @@
+#[cfg(feature = "ssr")]
+use super::article::{Feed, FeedOptions, FeedPosition};
+use super::article::{FeedFilter, TagMatch};
+
+/// Marks the start of a search hit in snippets
+pub const HIT_START: char = '\u{2}';
+/// Marks the end of a search hit in snippets
+pub const HIT_END: char = '\u{3}';
+
+/// Search query, parsed from the syntax of the search box:
+/// - `word` matches words with the same stem
+/// - `"some phrase"` matches the words in sequence
+/// - `prefix*` matches words starting with the prefix
+/// - `tag:name` and `author:name` limit the results
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// FTS5 match expression of the free text part
+    pub text: String,
+    pub tags: Vec<String>,
+    pub authors: Vec<String>,
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+        let mut terms = Vec::new();
+        let mut rest = query.trim_start();
+        while !rest.is_empty() {
+            let (token, quoted) = if let Some(phrase) = rest.strip_prefix('"') {
+                let end = phrase.find('"').unwrap_or(phrase.len());
+                rest = phrase.get(end + 1..).unwrap_or_default();
+                (&phrase[..end], true)
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let token = &rest[..end];
+                rest = &rest[end..];
+                (token, false)
+            };
+            rest = rest.trim_start();
+
+            if !quoted {
+                if let Some(tag) = token.strip_prefix("tag:").filter(|t| !t.is_empty()) {
+                    parsed.tags.push(tag.to_lowercase());
+                    continue;
+                }
+                if let Some(author) = token.strip_prefix("author:").filter(|a| !a.is_empty()) {
+                    parsed.authors.push(author.to_owned());
+                    continue;
+                }
+            }
+            if !token.chars().any(char::is_alphanumeric) {
+                // Would not match anything, or could be a syntax error
+                continue;
+            }
+            let (word, prefix) = match token.strip_suffix('*') {
+                Some(word) if !quoted => (word, true),
+                _ => (token, false),
+            };
+            // Everything is quoted, so that the FTS5 syntax can't be injected
+            let mut term = format!("\"{}\"", word.replace('"', "\"\""));
+            if prefix {
+                term.push('*');
+            }
+            terms.push(term);
+        }
+        parsed.text = terms.join(" ");
+        parsed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.tags.is_empty() && self.authors.is_empty()
+    }
+
+    /// Filter for the `tag:` and `author:` parts of the query
+    pub fn filter(&self) -> FeedFilter {
+        FeedFilter {
+            tags: self.tags.clone(),
+            tag_match: TagMatch::All,
+            authors: self.authors.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+#[derive(sqlx::FromRow)]
+struct SearchRow {
+    #[sqlx(flatten)]
+    article: super::article::ArticleRow,
+    snippet: Option<String>,
+}
+
+#[cfg(feature = "ssr")]
+fn push_conditions(
+    builder: &mut sqlx::QueryBuilder<'_, sqlx::Sqlite>,
+    query: &SearchQuery,
+    options: &FeedOptions,
+) {
+    if query.text.is_empty() {
+        builder.push(" where true");
+    } else {
+        builder
+            .push(" where article_fts match ")
+            .push_bind(query.text.clone());
+    }
+    let filter = query.filter();
+    filter.push_conditions(builder);
+    options.filter.push_conditions(builder);
+    options.push_muted_condition(builder, &filter);
+}
+
+#[cfg(feature = "ssr")]
+impl Feed {
+    /// Articles matching the query, best matches first. Snippets of the
+    /// matched text have the hits surrounded by [`HIT_START`] and [`HIT_END`].
+    pub async fn search(query: &str, options: &FeedOptions) -> Result<Self, sqlx::Error> {
+        use sqlx::QueryBuilder;
+
+        let query = SearchQuery::parse(query);
+        if query.is_empty() {
+            return Ok(Feed::default());
+        }
+
+        let tables = "
+            from article_fts
+            join article on article_fts.slug = article.slug
+            join user on article.author = user.username";
+
+        let mut count = QueryBuilder::new("select count(*) ");
+        count.push(tables);
+        push_conditions(&mut count, &query, options);
+        let count: i64 = count
+            .build_query_scalar()
+            .fetch_one(crate::db::get())
+            .await?;
+
+        let mut select = QueryBuilder::new(format!(
+            "select article.*, user.bio, user.image, {} as snippet ",
+            if query.text.is_empty() {
+                "null"
+            } else {
+                "snippet(article_fts, -1, char(2), char(3), '…', 24)"
+            }
+        ));
+        select.push(tables);
+        push_conditions(&mut select, &query, options);
+        if query.text.is_empty() {
+            select.push(" order by article.created_at desc");
+        } else {
+            // Weights by column: slug, title, description, body, tags
+            select.push(" order by bm25(article_fts, 0.0, 10.0, 5.0, 1.0, 5.0)");
+        }
+        // Relevance is not stable enough for positional paging
+        let offset = FeedPosition::page_offset(options.cursor.as_ref(), options.limit);
+        select
+            .push(" limit ")
+            .push_bind(options.limit)
+            .push(" offset ")
+            .push_bind(offset);
+        let rows: Vec<SearchRow> = select.build_query_as().fetch_all(crate::db::get()).await?;
+
+        let mut snippets = std::collections::HashMap::new();
+        let articles = rows
+            .into_iter()
+            .map(|row| {
+                if let Some(snippet) = row.snippet {
+                    snippets.insert(row.article.slug.clone(), snippet);
+                }
+                row.article
+            })
+            .collect();
+        let mut feed = super::article::fill_feed_details(articles, count as u32, options).await?;
+        feed.snippets = snippets;
+        let next = offset + u32::from(options.limit);
+        feed.prev = (offset > 0).then(|| FeedPosition::Offset(offset).token());
+        feed.next = (next < count as u32).then(|| FeedPosition::Offset(next).token());
+        Ok(feed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SearchQuery;
+
+    #[test]
+    fn empty_query() {
+        assert!(SearchQuery::parse("").is_empty());
+        assert!(SearchQuery::parse("   \t ").is_empty());
+        // Only punctuation would not match anything
+        assert!(SearchQuery::parse("- * \"\" ()").is_empty());
+    }
+
+    #[test]
+    fn words_are_quoted() {
+        let query = SearchQuery::parse("  rust  web ");
+        assert_eq!(query.text, r#""rust" "web""#);
+        assert!(query.tags.is_empty());
+        assert!(query.authors.is_empty());
+    }
+
+    #[test]
+    fn fts_syntax_is_escaped() {
+        assert_eq!(SearchQuery::parse("a\"b").text, r#""a""b""#);
+        assert_eq!(SearchQuery::parse("NOT rust").text, r#""NOT" "rust""#);
+        assert_eq!(SearchQuery::parse("title:rust").text, r#""title:rust""#);
+    }
+
+    #[test]
+    fn phrases() {
+        assert_eq!(
+            SearchQuery::parse(r#"say "hello world" again"#).text,
+            r#""say" "hello world" "again""#
+        );
+        // An unclosed phrase runs to the end
+        assert_eq!(
+            SearchQuery::parse(r#""hello world"#).text,
+            r#""hello world""#
+        );
+        // The star is part of the phrase, not a prefix
+        assert_eq!(SearchQuery::parse(r#""hel*""#).text, r#""hel*""#);
+    }
+
+    #[test]
+    fn prefixes() {
+        assert_eq!(SearchQuery::parse("hel*").text, r#""hel"*"#);
+    }
+
+    #[test]
+    fn operators() {
+        let query = SearchQuery::parse("tag:Rust async author:Jake tag:web");
+        assert_eq!(query.text, r#""async""#);
+        assert_eq!(query.tags, ["rust", "web"]);
+        assert_eq!(query.authors, ["Jake"]);
+        assert!(!query.is_empty());
+
+        let query = SearchQuery::parse("tag:rust");
+        assert!(query.text.is_empty());
+        assert!(!query.is_empty());
+    }
+
+    #[test]
+    fn operators_without_value_or_quoted_are_text() {
+        let query = SearchQuery::parse(r#"tag: "author:jake""#);
+        assert_eq!(query.text, r#""tag:" "author:jake""#);
+        assert!(query.tags.is_empty());
+        assert!(query.authors.is_empty());
+    }
+}
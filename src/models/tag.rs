@@ -0,0 +1,290 @@
+//! Tags of the articles, their descriptions and aliases, and the users
+//! following them
+
+use serde::{Deserialize, Serialize};
+
+/// Description and aliases of a tag, for its page
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TagMeta {
+    pub tag: String,
+    pub description: String,
+    /// Tag that this alias is replaced with, if it is one
+    pub alias_of: Option<String>,
+    /// Aliases that are replaced with this tag
+    pub aliases: Vec<String>,
+}
+
+#[cfg(feature = "ssr")]
+impl TagMeta {
+    pub async fn get(tag: &str) -> Result<Self, sqlx::Error> {
+        let row = sqlx::query!(
+            "select description, alias_of from tag_meta where tag = ?",
+            tag
+        )
+        .fetch_optional(crate::db::get())
+        .await?;
+        let aliases = sqlx::query_scalar!(
+            "select tag from tag_meta where alias_of = ? order by tag",
+            tag
+        )
+        .fetch_all(crate::db::get())
+        .await?;
+        let (description, alias_of) =
+            row.map_or_else(Default::default, |row| (row.description, row.alias_of));
+        Ok(Self {
+            tag: tag.to_owned(),
+            description,
+            alias_of,
+            aliases,
+        })
+    }
+
+    /// Tag that the alias is replaced with, or the tag itself
+    pub async fn canonical(tag: &str) -> Result<String, sqlx::Error> {
+        let alias_of = sqlx::query_scalar!("select alias_of from tag_meta where tag = ?", tag)
+            .fetch_optional(crate::db::get())
+            .await?
+            .flatten();
+        Ok(alias_of.unwrap_or_else(|| tag.to_owned()))
+    }
+
+    pub async fn set_description(tag: &str, description: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "
+            insert into tag_meta (tag, description) values (?, ?)
+            on conflict (tag) do update set
+                description = excluded.description,
+                updated_at = datetime('now')
+            ",
+            tag,
+            description
+        )
+        .execute(crate::db::get())
+        .await?;
+        Ok(())
+    }
+
+    /// Replaces the alias with the tag in the existing articles, and in the
+    /// ones saved from now on, along with the aliases of the alias itself.
+    /// The follows of the alias become follows of the tag.
+    ///
+    /// The tag must not be an alias, see [`TagMeta::canonical`].
+    pub async fn set_alias(alias: &str, tag: &str) -> Result<(), sqlx::Error> {
+        let mut tx = crate::db::get().begin().await?;
+        Self::alias_in(&mut tx, alias, tag).await?;
+        tx.commit().await
+    }
+
+    async fn alias_in(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        alias: &str,
+        tag: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "
+            insert or ignore into tag (tag, article)
+            select ?, article from tag where tag = ?
+            ",
+            tag,
+            alias
+        )
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query!("delete from tag where tag = ?", alias)
+            .execute(&mut **tx)
+            .await?;
+
+        sqlx::query!(
+            "
+            insert into tag_meta (tag, alias_of) values (?, ?)
+            on conflict (tag) do update set
+                alias_of = excluded.alias_of,
+                updated_at = datetime('now')
+            ",
+            alias,
+            tag
+        )
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query!(
+            "update tag_meta set alias_of = ?, updated_at = datetime('now') where alias_of = ?",
+            tag,
+            alias
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query!(
+            "
+            insert or ignore into tag_follow (user, tag, created_at)
+            select user, ?, created_at from tag_follow where tag = ?
+            ",
+            tag,
+            alias
+        )
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query!("delete from tag_follow where tag = ?", alias)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    /// Stops replacing the alias with its tag
+    pub async fn remove_alias(alias: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "update tag_meta set alias_of = null, updated_at = datetime('now') where tag = ?",
+            alias
+        )
+        .execute(crate::db::get())
+        .await?;
+        Ok(())
+    }
+
+    /// Makes the tag an alias of another one, like [`TagMeta::set_alias`],
+    /// and keeps its description if the other one doesn't have any.
+    ///
+    /// The other tag must not be an alias, see [`TagMeta::canonical`].
+    pub async fn merge(from: &str, into: &str) -> Result<(), sqlx::Error> {
+        let mut tx = crate::db::get().begin().await?;
+
+        // Keep the description, if the other tag doesn't have one yet
+        sqlx::query!(
+            "
+            insert into tag_meta (tag, description)
+            select ?, description from tag_meta where tag = ? and description != ''
+            on conflict (tag) do update set
+                description = excluded.description,
+                updated_at = datetime('now')
+            where tag_meta.description = ''
+            ",
+            into,
+            from
+        )
+        .execute(&mut *tx)
+        .await?;
+        Self::alias_in(&mut tx, from, into).await?;
+
+        tx.commit().await
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub struct TagFollow;
+
+#[cfg(feature = "ssr")]
+impl TagFollow {
+    /// Tags the user follows, alphabetically
+    pub async fn for_user(user: &str) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar!(
+            "select tag from tag_follow where user = ? order by tag",
+            user
+        )
+        .fetch_all(crate::db::get())
+        .await
+    }
+
+    pub async fn is_following(user: &str, tag: &str) -> Result<bool, sqlx::Error> {
+        Ok(sqlx::query_scalar!(
+            "select 1 from tag_follow where user = ? and tag = ?",
+            user,
+            tag
+        )
+        .fetch_optional(crate::db::get())
+        .await?
+        .is_some())
+    }
+
+    /// Follows the tag, or unfollows it. Returns whether it changed.
+    pub async fn set(user: &str, tag: &str, follow: bool) -> Result<bool, sqlx::Error> {
+        let res = if follow {
+            sqlx::query!(
+                "insert or ignore into tag_follow (user, tag) values (?, ?)",
+                user,
+                tag
+            )
+        } else {
+            sqlx::query!(
+                "delete from tag_follow where user = ? and tag = ?",
+                user,
+                tag
+            )
+        }
+        .execute(crate::db::get())
+        .await?;
+        Ok(res.rows_affected() == 1)
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::{TagFollow, TagMeta};
+    use crate::models::article::Article;
+
+    async fn tags_of(slug: &str) -> Vec<String> {
+        sqlx::query_scalar("select tag from tag where article = ? order by tag")
+            .bind(slug)
+            .fetch_all(crate::db::get())
+            .await
+            .expect("tags")
+    }
+
+    async fn article(title: &str, tags: &[&str]) -> String {
+        Article::create("alice", title, "Description", "Body", tags)
+            .await
+            .expect("created")
+            .expect("valid")
+    }
+
+    #[tokio::test]
+    async fn merged_tag_is_replaced_with_the_other() {
+        crate::db::init_with("sqlite::memory:").await;
+        for user in ["alice", "bob"] {
+            sqlx::query("insert into user (username, email, password) values (?, ?, ?)")
+                .bind(user)
+                .bind(format!("{}@example.com", user))
+                .bind("not a hash")
+                .execute(crate::db::get())
+                .await
+                .expect("user");
+        }
+        let js = article("Closures", &["js", "web"]).await;
+        let javascript = article("Promises", &["javascript"]).await;
+        let both = article("Modules", &["js", "javascript"]).await;
+        TagMeta::set_description("js", "Scripts in the browser")
+            .await
+            .unwrap();
+        TagMeta::set_alias("ecmascript", "js").await.unwrap();
+        TagFollow::set("alice", "js", true).await.unwrap();
+        TagFollow::set("alice", "javascript", true).await.unwrap();
+        TagFollow::set("bob", "js", true).await.unwrap();
+
+        TagMeta::merge("js", "javascript").await.expect("merged");
+
+        assert_eq!(tags_of(&js).await, ["javascript", "web"]);
+        assert_eq!(tags_of(&javascript).await, ["javascript"]);
+        assert_eq!(tags_of(&both).await, ["javascript"]);
+
+        assert_eq!(TagFollow::for_user("alice").await.unwrap(), ["javascript"]);
+        assert_eq!(TagFollow::for_user("bob").await.unwrap(), ["javascript"]);
+
+        let meta = TagMeta::get("javascript").await.unwrap();
+        assert_eq!(meta.aliases, ["ecmascript", "js"]);
+        assert_eq!(meta.description, "Scripts in the browser");
+        assert_eq!(
+            TagMeta::canonical("ecmascript").await.unwrap(),
+            "javascript"
+        );
+        assert_eq!(TagMeta::canonical("js").await.unwrap(), "javascript");
+
+        // The description of the other tag is kept, if it has one
+        article("Event loop", &["node"]).await;
+        TagMeta::set_description("node", "Server side")
+            .await
+            .unwrap();
+        TagMeta::merge("node", "javascript").await.expect("merged");
+        let meta = TagMeta::get("javascript").await.unwrap();
+        assert_eq!(meta.description, "Scripts in the browser");
+        assert_eq!(meta.aliases, ["ecmascript", "js", "node"]);
+    }
+}
@@ -1,11 +1,32 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
 pub struct Profile {
     pub username: String,
     pub bio: Option<String>,
     pub image: Option<String>,
     pub following: bool,
+    /// Whether the profile follows the viewer
+    pub follows_you: bool,
+    pub followers_count: u32,
+    pub following_count: u32,
+}
+
+/// Which follows of a user are listed
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FollowList {
+    /// Users following them
+    Followers,
+    /// Users they follow
+    Following,
+}
+
+/// Page of the followers or followed users of a user
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ProfilePage {
+    pub profiles: Vec<Profile>,
+    /// Cursor for the next page, if there is one
+    pub next: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
@@ -14,40 +35,109 @@ pub struct User {
     pub email: String,
     pub bio: Option<String>,
     pub image: Option<String>,
+    /// Moderators can see e.g. the edit history of comments
+    #[serde(default)]
+    pub moderator: bool,
 }
 
 #[cfg(feature = "ssr")]
 impl User {
     pub async fn profile(username: &str, for_user: Option<&str>) -> Result<Profile, sqlx::Error> {
-        let mut profile = sqlx::query!(
-            "select username, bio, image from user where username = ?",
+        sqlx::query!(
+            r#"
+            select username, bio, image,
+                exists(select 1 from follow where follower = ? and followed = username)
+                    as "following!: bool",
+                exists(select 1 from follow where follower = username and followed = ?)
+                    as "follows_you!: bool",
+                (select count(*) from follow where followed = username) as "followers_count!: u32",
+                (select count(*) from follow where follower = username) as "following_count!: u32"
+            from user where username = ?
+            "#,
+            for_user,
+            for_user,
             username,
         )
         .map(|row| Profile {
             username: row.username,
             bio: row.bio,
             image: row.image,
-            following: false,
+            following: row.following,
+            follows_you: row.follows_you,
+            followers_count: row.followers_count,
+            following_count: row.following_count,
         })
         .fetch_one(crate::db::get())
-        .await?;
-        if let Some(user) = for_user {
-            profile.following = sqlx::query_scalar!(
-                "select 1 from follow where follower = ? and followed = ?",
-                user,
-                username
+        .await
+    }
+
+    /// Followers of the user, or the users they follow, by username. The
+    /// follow states are of the viewer.
+    pub async fn follows(
+        username: &str,
+        list: FollowList,
+        viewer: Option<&str>,
+        after: Option<&str>,
+        limit: u8,
+    ) -> Result<ProfilePage, sqlx::Error> {
+        use super::article::{decode_token, encode_token};
+
+        let (listed, of) = match list {
+            FollowList::Followers => ("follower", "followed"),
+            FollowList::Following => ("followed", "follower"),
+        };
+        let mut query = sqlx::QueryBuilder::new(
+            "
+            select user.username, user.bio, user.image,
+                exists(select 1 from follow as f where f.follower = ",
+        );
+        query
+            .push_bind(viewer)
+            .push(
+                " and f.followed = user.username),
+                exists(select 1 from follow as f where f.follower = user.username and f.followed = ",
             )
-            .fetch_optional(crate::db::get())
-            .await?
-            .is_some();
+            .push_bind(viewer)
+            .push(format!(
+                ")
+                from follow join user on user.username = follow.{}
+                where follow.{} = ",
+                listed, of
+            ))
+            .push_bind(username);
+        if let Some(after) = after.and_then(decode_token) {
+            query.push(" and user.username > ").push_bind(after);
         }
-        Ok(profile)
+        query
+            .push(" order by user.username limit ")
+            .push_bind(i64::from(limit) + 1);
+
+        let mut rows: Vec<(String, Option<String>, Option<String>, bool, bool)> =
+            query.build_query_as().fetch_all(crate::db::get()).await?;
+        let more = rows.len() > usize::from(limit);
+        rows.truncate(limit.into());
+        let next = rows
+            .last()
+            .filter(|_| more)
+            .map(|(username, ..)| encode_token(username));
+        let profiles = rows
+            .into_iter()
+            .map(|(username, bio, image, following, follows_you)| Profile {
+                username,
+                bio,
+                image,
+                following,
+                follows_you,
+                ..Default::default()
+            })
+            .collect();
+        Ok(ProfilePage { profiles, next })
     }
 
     pub async fn get(username: &str) -> Result<Self, sqlx::Error> {
         sqlx::query_as!(
             Self,
-            "select username, email, bio, image from user where username = ?",
+            r#"select username, email, bio, image, moderator as "moderator: bool" from user where username = ?"#,
             username
         )
         .fetch_one(crate::db::get())
@@ -69,6 +159,7 @@ impl User {
             email: email.to_owned(),
             bio: None,
             image: None,
+            moderator: false,
         })
     }
 
@@ -108,4 +199,48 @@ impl User {
         }
         Ok(())
     }
+
+    /// Secret token for the user's private feed URL, created on first use
+    pub async fn feed_token(username: &str) -> Result<String, sqlx::Error> {
+        let token = sqlx::query_scalar!("select token from feed_token where user = ?", username)
+            .fetch_optional(crate::db::get())
+            .await?;
+        match token {
+            Some(token) => Ok(token),
+            None => Self::reset_feed_token(username).await,
+        }
+    }
+
+    /// Replaces the feed token, so the previous private feed URL stops working
+    pub async fn reset_feed_token(username: &str) -> Result<String, sqlx::Error> {
+        let token = random_token();
+        sqlx::query!(
+            "
+            insert into feed_token (user, token) values (?, ?)
+            on conflict (user) do update set token = excluded.token, created_at = datetime('now')
+            ",
+            username,
+            token
+        )
+        .execute(crate::db::get())
+        .await?;
+        Ok(token)
+    }
+
+    /// Username of the owner of the feed token
+    pub async fn by_feed_token(token: &str) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar!("select user from feed_token where token = ?", token)
+            .fetch_optional(crate::db::get())
+            .await
+    }
+}
+
+/// Random hex token for secret URLs
+#[cfg(feature = "ssr")]
+pub(crate) fn random_token() -> String {
+    use argon2::password_hash::rand_core::{OsRng, RngCore};
+
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
 }
@@ -1,12 +1,22 @@
 use crate::{
     app::{use_current_user, ArticleSlugParam, FollowButton, TagList, NBSP},
     error_template::error_boundary_fallback,
-    models::{article::Article, comment::Comment},
-    pages::profile::{profile_link, ProfileImg},
+    models::{
+        article::{Article, FeedReason},
+        comment::{Comment, CommentEvent, CommentPage, CommentRevision, CommentSort},
+        search::{HIT_END, HIT_START},
+    },
+    pages::{
+        mention::MentionSuggestions,
+        meta::ArticlePageMeta,
+        profile::{profile_link, ProfileImg},
+        reaction::{ReactionBar, ReactionCounts},
+    },
 };
 use leptos::*;
 use leptos_meta::Script;
 use leptos_router::*;
+use wasm_bindgen::{closure::Closure, JsCast};
 
 #[component]
 pub fn Article() -> impl IntoView {
@@ -24,7 +34,15 @@ pub fn Article() -> impl IntoView {
             <Suspense fallback=|| "Loading article...">
                 <ErrorBoundary fallback=error_boundary_fallback>
                     {move || {
-                        article().map(|res| res.map(|article| view! { <ArticleContent article/> }))
+                        article()
+                            .map(|res| {
+                                res.map(|article| {
+                                    view! {
+                                        <ArticlePageMeta article=article.clone()/>
+                                        <ArticleContent article/>
+                                    }
+                                })
+                            })
                     }}
 
                 </ErrorBoundary>
@@ -36,18 +54,70 @@ pub fn Article() -> impl IntoView {
     }
 }
 
+/// Renders search result snippet, with the hits highlighted
+fn highlighted(snippet: &str) -> View {
+    snippet
+        .split(HIT_START)
+        .enumerate()
+        .map(|(i, part)| {
+            if i == 0 {
+                part.to_owned().into_view()
+            } else {
+                let (hit, rest) = part.split_once(HIT_END).unwrap_or((part, ""));
+                view! {
+                    <mark>{hit.to_owned()}</mark>
+                    {rest.to_owned()}
+                }
+                .into_view()
+            }
+        })
+        .collect_view()
+}
+
 #[component]
-pub fn ArticlePreview(#[prop(into)] article: RwSignal<Article>) -> impl IntoView {
+pub fn ArticlePreview(
+    #[prop(into)] article: RwSignal<Article>,
+    #[prop(optional)] snippet: Option<String>,
+    /// Why it's in the personal feed
+    #[prop(optional)]
+    reason: Option<FeedReason>,
+) -> impl IntoView {
     let article_link = move || article.with(|a| format!("/article/{}", a.slug));
+    let reason = reason.map(|reason| {
+        let (href, name) = match reason {
+            FeedReason::Author(author) => (profile_link(&author), author),
+            FeedReason::Tag(tag) => (format!("/tag/{}", tag), format!("#{}", tag)),
+        };
+        view! {
+            <p class="feed-reason text-muted">
+                "Because you follow " <A href=href>{name}</A>
+            </p>
+        }
+    });
     view! {
         <div class="article-preview">
+            {reason}
             <ArticleMeta article=article>
                 <FavoriteButton article=article compact=true/>
+                <BookmarkButton article=article compact=true/>
+                <Show when=move || article.with(|a| a.bookmarked)>
+                    <ReadToggle article=article/>
+                </Show>
             </ArticleMeta>
             <A href=article_link class="preview-link">
                 <h1>{move || article.with(|a| a.title.clone())}</h1>
                 <p>{move || article.with(|a| a.description.clone())}</p>
+                {snippet.map(|s| view! { <p class="snippet">{highlighted(&s)}</p> })}
                 <span>Read more...</span>
+                <span class="comments-count pull-xs-right">
+                    <ReactionCounts reactions=Signal::derive(move || {
+                        article.with(|a| a.reactions.clone())
+                    })/>
+                    {NBSP}
+                    <i class="ion-chatbubbles"></i>
+                    {NBSP}
+                    {move || article.with(|a| a.comments_count)}
+                </span>
             </A>
             <TagList outline=true tags=move || article.with(|a| a.tags.clone())/>
         </div>
@@ -72,7 +142,7 @@ async fn toggle_favorite(article: String, current: bool) -> Result<bool, ServerF
         tracing::debug!("own article");
         return Ok(false);
     }
-    if current {
+    let changed = if current {
         sqlx::query!(
             "delete from favorite where user = ? and article = ?",
             logged_in,
@@ -94,7 +164,15 @@ async fn toggle_favorite(article: String, current: bool) -> Result<bool, ServerF
     .map_err(|e| {
         tracing::error!("failed to toggle follow: {:?}", e);
         ServerFnError::ServerError("database error".into())
-    })
+    })?;
+    if changed && !current {
+        let notified =
+            crate::models::notification::Notification::notify_favorite(&article, &logged_in).await;
+        if let Err(e) = notified {
+            tracing::error!("failed to notify of favorite: {:?}", e);
+        }
+    }
+    Ok(changed)
 }
 
 #[component]
@@ -143,6 +221,105 @@ fn FavoriteButton(article: RwSignal<Article>, #[prop(optional)] compact: bool) -
     }
 }
 
+/// Adds the article to the reading list, or removes it
+#[server]
+async fn toggle_bookmark(article: String, current: bool) -> Result<bool, ServerFnError> {
+    let logged_in = crate::auth::require_login()?;
+    crate::models::bookmark::Bookmark::set(&logged_in, &article, !current)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to toggle bookmark: {:?}", e);
+            ServerFnError::ServerError("database error".into())
+        })
+}
+
+#[component]
+fn BookmarkButton(article: RwSignal<Article>, #[prop(optional)] compact: bool) -> impl IntoView {
+    let user = use_current_user();
+    let toggle = create_server_action::<ToggleBookmark>();
+    let pending = toggle.pending();
+    let result = toggle.value();
+    let bookmarked = move || article.with(|a| a.bookmarked);
+
+    create_effect(move |_| {
+        let success = result.with(|res| matches!(res, Some(Ok(true))));
+        if success {
+            article.update(|a| {
+                a.bookmarked = !a.bookmarked;
+                a.bookmark_read = false;
+            });
+        }
+    });
+
+    let text = move || match (compact, bookmarked()) {
+        (true, _) => "",
+        (false, true) => "Remove from reading list",
+        (false, false) => "Read later",
+    };
+
+    view! {
+        <Show when=move || user.with(Option::is_some)>
+            <ActionForm action=toggle>
+                <button
+                    type="submit"
+                    disabled=pending
+                    class="btn btn-sm"
+                    class:btn-outline-secondary=move || !bookmarked()
+                    class:btn-secondary=bookmarked
+                    title=move || if bookmarked() { "Remove from reading list" } else { "Read later" }
+                >
+                    <i class="ion-bookmark"></i>
+                    {NBSP}
+                    {text}
+                </button>
+                <input type="hidden" name="article" value=move || article.with(|a| a.slug.clone())/>
+                <input type="hidden" name="current" value=move || bookmarked().to_string()/>
+            </ActionForm>
+        </Show>
+    }
+}
+
+#[server]
+async fn mark_bookmark_read(article: String, read: bool) -> Result<(), ServerFnError> {
+    let logged_in = crate::auth::require_login()?;
+    crate::models::bookmark::Bookmark::mark_read(&logged_in, &article, read)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to mark bookmark read: {:?}", e);
+            ServerFnError::ServerError("database error".into())
+        })
+}
+
+/// Read state of a bookmarked article, which can be marked unread again
+#[component]
+fn ReadToggle(article: RwSignal<Article>) -> impl IntoView {
+    let mark = create_server_action::<MarkBookmarkRead>();
+    let read = move || article.with(|a| a.bookmark_read);
+
+    create_effect(move |_| {
+        if let Some(Ok(())) = mark.value()() {
+            article.update(|a| a.bookmark_read = !a.bookmark_read);
+        }
+    });
+
+    view! {
+        <ActionForm action=mark>
+            <input type="hidden" name="article" value=move || article.with(|a| a.slug.clone())/>
+            <input type="hidden" name="read" value=move || (!read()).to_string()/>
+            <button
+                type="submit"
+                disabled=mark.pending()
+                class="btn btn-sm btn-link"
+                title=move || if read() { "Mark unread" } else { "Mark read" }
+            >
+                <span class="tag-pill tag-default" class:tag-outline=read>
+                    {move || if read() { "Read" } else { "Unread" }}
+                </span>
+            </button>
+        </ActionForm>
+    }
+}
+
 #[component]
 fn ArticleMeta(#[prop(into)] article: Signal<Article>, children: Children) -> impl IntoView {
     let author = Signal::derive(move || article.with(|a| a.author.clone()));
@@ -176,6 +353,13 @@ fn ArticleMeta(#[prop(into)] article: Signal<Article>, children: Children) -> im
 async fn get_article(slug: String) -> Result<Article, ServerFnError> {
     tracing::info!("fetching article: {}", slug);
     let user = crate::auth::authenticated_username();
+    if let Some(user) = &user {
+        // Opening the article reads it from the reading list
+        let marked = crate::models::bookmark::Bookmark::mark_read(user, &slug, true).await;
+        if let Err(e) = marked {
+            tracing::error!("failed to mark bookmark read: {:?}", e);
+        }
+    }
     Ok(Article::get(&slug, user.as_deref()).await?)
 }
 
@@ -215,6 +399,7 @@ fn ArticleActions(#[prop(into)] article: RwSignal<Article>) -> impl IntoView {
                             <FollowButton profile=profile/>
                         </Show>
                         <FavoriteButton article=article/>
+                        <BookmarkButton article=article/>
                     }
                 }
             >
@@ -252,6 +437,8 @@ fn ArticleActions(#[prop(into)] article: RwSignal<Article>) -> impl IntoView {
 fn ArticleContent(article: Article) -> impl IntoView {
     // The body is not affected by ArticleActions
     let body = article.body.clone();
+    let slug = article.slug.clone();
+    let reactions = article.reactions.clone();
     let md_src = format!(
         "/raw/article/{}/{}",
         &article.author.username, &article.slug
@@ -276,6 +463,7 @@ fn ArticleContent(article: Article) -> impl IntoView {
                         <zero-md src=md_src></zero-md>
                     </div>
                     <TagList outline=true tags=move || article.with(|a| a.tags.clone())/>
+                    <ReactionBar article=slug reactions=reactions/>
                 </div>
             </div>
 
@@ -288,28 +476,144 @@ fn ArticleContent(article: Article) -> impl IntoView {
     }
 }
 
+/// Number of threads on a page of comments
+const COMMENT_PAGE_SIZE: u8 = 20;
+
 #[server]
-async fn comments(slug: String) -> Result<Vec<Comment>, ServerFnError> {
-    Ok(Comment::for_article(&slug).await?)
+async fn comments(
+    slug: String,
+    sort: CommentSort,
+    after: Option<String>,
+) -> Result<CommentPage, ServerFnError> {
+    let viewer = crate::auth::authenticated_username();
+    Ok(Comment::for_article(
+        &slug,
+        sort,
+        after.as_deref(),
+        COMMENT_PAGE_SIZE,
+        viewer.as_deref(),
+    )
+    .await?)
 }
 
 #[server]
-async fn delete_comment(id: i64) -> Result<(), ServerFnError> {
+async fn delete_comment(id: i64) -> Result<Vec<CommentEvent>, ServerFnError> {
     let author = crate::auth::require_login()?;
-    sqlx::query!("delete from comment where id = ? and user = ?", id, author)
-        .execute(crate::db::get())
-        .await?;
-    Ok(())
+    Ok(Comment::delete(id, &author).await?)
+}
+
+#[server]
+async fn edit_comment(id: i64, comment: String) -> Result<Vec<CommentEvent>, ServerFnError> {
+    let author = crate::auth::require_login()?;
+    Comment::edit(id, &author, &comment)
+        .await
+        .map(|edited| edited.map(CommentEvent::Updated).into_iter().collect())
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => {
+                ServerFnError::ServerError("The comment can no longer be edited".into())
+            }
+            e => {
+                tracing::error!("failed to edit comment: {:?}", e);
+                ServerFnError::ServerError("Could not edit comment".into())
+            }
+        })
+}
+
+#[server]
+async fn comment_history(id: i64) -> Result<Vec<CommentRevision>, ServerFnError> {
+    crate::auth::require_moderator().await?;
+    Ok(Comment::history(id).await?)
+}
+
+/// Previous versions of a comment for moderators, loaded when opened
+#[component]
+fn CommentHistory(id: i64, edited_at: String) -> impl IntoView {
+    let details: NodeRef<html::Details> = create_node_ref();
+    let (open, set_open) = create_signal(false);
+    let history = create_resource(open, move |open| async move {
+        if open {
+            comment_history(id).await.map(Some)
+        } else {
+            Ok(None)
+        }
+    });
+    let revisions = move || {
+        history().map(|res| {
+            res.map(|revisions| {
+                revisions
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|revision| {
+                        view! {
+                            <li>
+                                <span class="date-posted">{revision.written_at}</span>
+                                <p class="card-text">{revision.body}</p>
+                            </li>
+                        }
+                    })
+                    .collect_view()
+            })
+        })
+    };
+    view! {
+        <details
+            class="comment-history"
+            node_ref=details
+            on:toggle=move |_| set_open(details().is_some_and(|d| d.open()))
+        >
+            <summary class="date-posted">"(edited " {edited_at} ")"</summary>
+            <Transition fallback=|| "Loading history...">
+                <ErrorBoundary fallback=error_boundary_fallback>
+                    <ul>{revisions}</ul>
+                </ErrorBoundary>
+            </Transition>
+        </details>
+    }
 }
 
 #[component]
-fn CommentCard(comment: Comment, children: Children) -> impl IntoView {
+fn CommentCard(
+    comment: Comment,
+    /// Show the edit history, for moderators
+    #[prop(optional)]
+    history: bool,
+    children: Children,
+) -> impl IntoView {
+    if comment.hidden {
+        return view! {
+            <div class="card">
+                <div class="card-block">
+                    <p class="card-text text-muted">"[comment by a muted user]"</p>
+                </div>
+            </div>
+        };
+    }
+    if comment.deleted {
+        let id = comment.id;
+        return view! {
+            <div class="card">
+                <div class="card-block">
+                    <p class="card-text text-muted">"[deleted]"</p>
+                </div>
+                {comment
+                    .edited_at
+                    .filter(|_| history)
+                    .map(|edited_at| {
+                        view! {
+                            <div class="card-footer">
+                                <CommentHistory id edited_at/>
+                            </div>
+                        }
+                    })}
+            </div>
+        };
+    }
     let author = comment.author.clone();
     let link = profile_link(&author.username);
     view! {
         <div class="card">
             <div class="card-block">
-                <p class="card-text">{&comment.body}</p>
+                <div class="card-text comment-body" inner_html=comment.body_html.clone()></div>
             </div>
             <div
                 class="card-footer"
@@ -322,6 +626,21 @@ fn CommentCard(comment: Comment, children: Children) -> impl IntoView {
                     {&author.username}
                 </A>
                 <span class="date-posted">{&comment.created_at}</span>
+                {comment
+                    .edited_at
+                    .clone()
+                    .map(|edited_at| {
+                        if history {
+                            view! { <CommentHistory id=comment.id edited_at/> }.into_view()
+                        } else {
+                            view! {
+                                <span class="date-posted" title=edited_at>
+                                    "(edited)"
+                                </span>
+                            }
+                                .into_view()
+                        }
+                    })}
                 {children()}
             </div>
         </div>
@@ -329,9 +648,211 @@ fn CommentCard(comment: Comment, children: Children) -> impl IntoView {
 }
 
 #[server]
-async fn post_comment(article: String, comment: String) -> Result<i64, ServerFnError> {
+async fn post_comment(
+    article: String,
+    comment: String,
+    parent_id: Option<i64>,
+) -> Result<Vec<CommentEvent>, ServerFnError> {
     let user = crate::auth::require_login()?;
-    Ok(Comment::create(&article, &user, &comment).await?)
+    let author = sqlx::query_scalar!("select author from article where slug = ?", article)
+        .fetch_one(crate::db::get())
+        .await?;
+    if crate::models::mute::MutedUser::is_blocked(&author, &user).await? {
+        return Err(ServerFnError::ServerError(
+            "The author has blocked you from commenting".into(),
+        ));
+    }
+    let comment = Comment::create(&article, &user, &comment, parent_id).await?;
+    let notified =
+        crate::models::notification::Notification::notify_comment(comment.id, &user).await;
+    if let Err(e) = notified {
+        tracing::error!("failed to notify of comment: {:?}", e);
+    }
+    Ok(vec![CommentEvent::Created(comment)])
+}
+
+/// The comment actions give the changes they made, for merging them in like
+/// the live ones
+type PostCommentAction = Action<PostComment, Result<Vec<CommentEvent>, ServerFnError>>;
+type DeleteCommentAction = Action<DeleteComment, Result<Vec<CommentEvent>, ServerFnError>>;
+type EditCommentAction = Action<EditComment, Result<Vec<CommentEvent>, ServerFnError>>;
+
+/// Comment with its replies, which can be collapsed. Works without JS as the
+/// collapsing and the reply form use `<details>`.
+#[component]
+fn CommentThread(
+    mut comment: Comment,
+    article_slug: Signal<String>,
+    user: Option<String>,
+    moderator: bool,
+    post: PostCommentAction,
+    delete: DeleteCommentAction,
+    edit: EditCommentAction,
+) -> impl IntoView {
+    let id = comment.id;
+    let is_author = !comment.deleted && user.as_deref() == Some(&comment.author.username);
+    let reply_count = comment.reply_count();
+    let replies = std::mem::take(&mut comment.replies);
+    let reactions = (!comment.deleted && !comment.hidden).then(|| {
+        let reactions = std::mem::take(&mut comment.reactions);
+        view! { <ReactionBar article=article_slug comment=id reactions=reactions/> }
+    });
+
+    // Now a single action is shared between all comments, and thus
+    // all buttons will be disabled while one delete is pending.
+    //
+    // This is just fine.
+    let delete_button = is_author.then(|| {
+        view! {
+            <ActionForm action=delete>
+                <input type="hidden" name="id" value=id/>
+                <button
+                    type="submit"
+                    disabled=delete.pending()
+                    class="btn btn-sm btn-outline-danger"
+                >
+                    <i class="ion-trash-a"></i>
+                </button>
+            </ActionForm>
+        }
+    });
+    let edit_ref: NodeRef<html::Textarea> = create_node_ref();
+    let reply_ref: NodeRef<html::Textarea> = create_node_ref();
+    let edit_form = (is_author && comment.editable).then(|| {
+        view! {
+            <details class="comment-edit">
+                <summary class="btn btn-sm btn-outline-secondary">"Edit"</summary>
+                <ActionForm class="card comment-form" action=edit>
+                    <input type="hidden" name="id" value=id/>
+                    <div class="card-block">
+                        <textarea node_ref=edit_ref class="form-control" rows="3" name="comment">
+                            {comment.body.clone()}
+                        </textarea>
+                        <MentionSuggestions textarea=edit_ref/>
+                    </div>
+                    <div class="card-footer">
+                        <button
+                            type="submit"
+                            disabled=edit.pending()
+                            class="btn btn-sm btn-primary"
+                        >
+                            Save
+                        </button>
+                    </div>
+                </ActionForm>
+            </details>
+        }
+    });
+    let reply_form = user.is_some().then(|| {
+        view! {
+            <details class="comment-reply">
+                <summary class="btn btn-sm btn-outline-secondary">"Reply"</summary>
+                <ActionForm class="card comment-form" action=post>
+                    <input type="hidden" name="article" value=article_slug/>
+                    <input type="hidden" name="parent_id" value=id/>
+                    <div class="card-block">
+                        <textarea
+                            node_ref=reply_ref
+                            class="form-control"
+                            placeholder="Write a reply..."
+                            rows="2"
+                            name="comment"
+                        ></textarea>
+                        <MentionSuggestions textarea=reply_ref/>
+                    </div>
+                    <div class="card-footer">
+                        <button type="submit" class="btn btn-sm btn-primary">
+                            Post Reply
+                        </button>
+                    </div>
+                </ActionForm>
+            </details>
+        }
+    });
+
+    view! {
+        <div class="comment-thread">
+            <CommentCard comment=comment history=moderator>
+                {reactions}
+                {delete_button}
+            </CommentCard>
+            {edit_form}
+            {reply_form}
+            {(reply_count != 0)
+                .then(|| {
+                    view! {
+                        <details class="comment-replies" open style="margin-left: 1.5rem">
+                            <summary>
+                                {reply_count}
+                                {if reply_count == 1 { " reply" } else { " replies" }}
+                            </summary>
+                            {replies
+                                .into_iter()
+                                .map(|reply| {
+                                    view! {
+                                        <CommentThread
+                                            comment=reply
+                                            article_slug
+                                            user=user.clone()
+                                            moderator
+                                            post
+                                            delete
+                                            edit
+                                        />
+                                    }
+                                        .into_view()
+                                })
+                                .collect_view()}
+                        </details>
+                    }
+                })}
+        </div>
+    }
+}
+
+/// Live comment events of an article, closed when dropped
+struct CommentSubscription {
+    source: web_sys::EventSource,
+    _listeners: [Closure<dyn FnMut(web_sys::MessageEvent)>; 2],
+}
+
+impl CommentSubscription {
+    /// Subscribes to the events. The browser reconnects on its own, and gets
+    /// the missed events with the `Last-Event-ID`.
+    fn new(
+        slug: &str,
+        on_event: impl Fn(CommentEvent) + 'static,
+        on_resync: impl Fn() + 'static,
+    ) -> Option<Self> {
+        let source =
+            web_sys::EventSource::new(&format!("/article/{}/comments/events", slug)).ok()?;
+        let comment = Closure::<dyn FnMut(_)>::new(move |ev: web_sys::MessageEvent| {
+            if let Some(event) = ev
+                .data()
+                .as_string()
+                .and_then(|data| serde_json::from_str(&data).ok())
+            {
+                on_event(event);
+            }
+        });
+        let resync = Closure::<dyn FnMut(_)>::new(move |_: web_sys::MessageEvent| on_resync());
+        source
+            .add_event_listener_with_callback("comment", comment.as_ref().unchecked_ref())
+            .ok()?;
+        source
+            .add_event_listener_with_callback("resync", resync.as_ref().unchecked_ref())
+            .ok()?;
+        Some(Self {
+            source,
+            _listeners: [comment, resync],
+        })
+    }
+}
+
+impl Drop for CommentSubscription {
+    fn drop(&mut self) {
+        self.source.close();
+    }
 }
 
 #[component]
@@ -339,11 +860,22 @@ fn Comments(#[prop(into)] article_slug: Signal<String>) -> impl IntoView {
     let user = use_current_user();
     let delete = create_server_action::<DeleteComment>();
     let post = create_server_action::<PostComment>();
+    let edit = create_server_action::<EditComment>();
     let post_result = post.value();
 
+    let query = use_query_map();
+    let sort = create_memo(move |_| {
+        query.with(|q| {
+            q.get("sort")
+                .map(|s| CommentSort::from_param(s))
+                .unwrap_or_default()
+        })
+    });
+    let after = create_memo(move |_| query.with(|q| q.get("comments_after").cloned()));
+
     let comments = create_resource(
-        move || (article_slug(), post.version()(), delete.version()()),
-        |(slug, _, _)| comments(slug),
+        move || (article_slug(), sort(), after()),
+        |(slug, sort, after)| comments(slug, sort, after),
     );
 
     let comment_ref: NodeRef<html::Textarea> = create_node_ref();
@@ -366,47 +898,146 @@ fn Comments(#[prop(into)] article_slug: Signal<String>) -> impl IntoView {
         }
     });
 
-    let delete_button = move |id: i64| {
-        // Now a single action is shared between all comments, and thus
-        // all buttons will be disabled while one delete is pending.
-        //
-        // This is just fine.
+    // Pages loaded after the first one, without navigating
+    let more = create_rw_signal(Vec::<Comment>::new());
+    let next = create_rw_signal(None::<String>);
+    let load_more = create_action(move |after: &String| {
+        comments(
+            article_slug.get_untracked(),
+            sort.get_untracked(),
+            Some(after.clone()),
+        )
+    });
+    create_effect(move |_| {
+        if let Some(Ok(page)) = load_more.value()() {
+            more.update(|m| m.extend(page.comments));
+            next.set(page.next);
+        }
+    });
+
+    // Changes since the comments were loaded, merged in without refetching.
+    // Without JS the comments are just as they were when the page was loaded.
+    let live = create_rw_signal(Vec::<CommentEvent>::new());
+    // Another article, sort or page starts over from its first page
+    create_effect(move |_| {
+        article_slug.track();
+        sort.track();
+        after.track();
+        more.set(Vec::new());
+        next.set(None);
+    });
+    // Reloading, e.g. to resync, keeps the pages loaded after the first one
+    create_effect(move |_| {
+        let first_next = comments.with(|page| page.as_ref().ok().and_then(|p| p.next.clone()));
+        if more.with_untracked(Vec::is_empty) {
+            next.set(first_next.flatten());
+        }
+        live.set(Vec::new());
+    });
+    create_effect(move |previous: Option<Option<CommentSubscription>>| {
+        // Closes the previous article's subscription
+        drop(previous);
+        CommentSubscription::new(
+            &article_slug(),
+            move |event| live.update(|events| events.push(event)),
+            move || comments.refetch(),
+        )
+    });
+    // Own changes arrive as live events too, but maybe only after the action
+    for changes in [post.value(), edit.value(), delete.value()] {
+        create_effect(move |_| {
+            if let Some(Ok(events)) = changes() {
+                live.update(|live| live.extend(events));
+            }
+        });
+    }
+
+    let load_more_link = move || {
+        next().map(|cursor| {
+            let href = format!(
+                "?sort={}&comments_after={}#comments",
+                sort().param(),
+                cursor
+            );
+            let on_click = move |ev: ev::MouseEvent| {
+                ev.prevent_default();
+                if !load_more.pending().get_untracked() {
+                    load_more.dispatch(cursor.clone());
+                }
+            };
+            view! {
+                <a class="btn btn-sm btn-outline-primary" href=href on:click=on_click>
+                    "Load more comments"
+                </a>
+            }
+        })
+    };
+
+    let sort_link = move |option: CommentSort, text: &'static str| {
         view! {
-            <ActionForm action=delete>
-                <input type="hidden" name="id" value=id/>
-                <button
-                    type="submit"
-                    disabled=delete.pending()
-                    class="btn btn-sm btn-outline-danger"
+            <li class="nav-item">
+                <a
+                    class="nav-link"
+                    class:active=move || sort() == option
+                    href=format!("?sort={}#comments", option.param())
                 >
-                    <i class="ion-trash-a"></i>
-                </button>
-            </ActionForm>
+                    {text}
+                </a>
+            </li>
         }
     };
 
-    // TODO: maybe "subscribe" for new comments and update real time
     let comment_list = move || {
         comments().map(|data| {
-            data.map(|comments| {
-                let user = user.with(|u| u.as_ref().map(|u| u.username.clone()));
-                comments
-                    .into_iter()
-                    .map(|comment| {
-                        let id = comment.id;
-                        if user.as_deref() == Some(&comment.author.username) {
-                            view! { <CommentCard comment=comment>{delete_button(id)}</CommentCard> }
-                        } else {
-                            view! { <CommentCard comment=comment>""</CommentCard> }
+            data.map(
+                |CommentPage {
+                     mut comments,
+                     mut count,
+                     ..
+                 }| {
+                    more.with(|more| {
+                        for thread in more {
+                            if comments.iter().all(|c| c.id != thread.id) {
+                                comments.push(thread.clone());
+                            }
                         }
-                    })
-                    .collect_view()
-            })
+                    });
+                    let sort = sort();
+                    live.with(|events| {
+                        count += events
+                            .iter()
+                            .map(|event| event.apply(&mut comments, sort))
+                            .sum::<i64>();
+                    });
+                    let moderator = user.with(|u| u.as_ref().is_some_and(|u| u.moderator));
+                    let user = user.with(|u| u.as_ref().map(|u| u.username.clone()));
+                    view! {
+                        <p class="comment-count">
+                            {count} {if count == 1 { " comment" } else { " comments" }}
+                        </p>
+                        {comments
+                            .into_iter()
+                            .map(|comment| {
+                                view! {
+                                    <CommentThread
+                                        comment
+                                        article_slug
+                                        user=user.clone()
+                                        moderator
+                                        post
+                                        delete
+                                        edit
+                                    />
+                                }
+                            })
+                            .collect_view()}
+                    }
+                },
+            )
         })
     };
-
     view! {
-        <div class="col-xs-12 col-md-8 offset-md-2">
+        <div id="comments" class="col-xs-12 col-md-8 offset-md-2">
             <ActionForm class="card comment-form" action=post>
                 <input type="hidden" name="article" value=article_slug/>
                 <div class="card-block">
@@ -417,6 +1048,7 @@ fn Comments(#[prop(into)] article_slug: Signal<String>) -> impl IntoView {
                         rows="3"
                         name="comment"
                     ></textarea>
+                    <MentionSuggestions textarea=comment_ref/>
                 </div>
                 <div class="card-footer">
                     <ProfileImg src=None class="comment-author-img"/>
@@ -425,9 +1057,15 @@ fn Comments(#[prop(into)] article_slug: Signal<String>) -> impl IntoView {
                     </button>
                 </div>
             </ActionForm>
+            <ul class="nav nav-pills outline-active comment-sort">
+                {sort_link(CommentSort::Oldest, "Oldest")}
+                {sort_link(CommentSort::Newest, "Newest")}
+                {sort_link(CommentSort::MostReacted, "Most reacted")}
+            </ul>
             <Transition fallback=move || "Loading comments...">
                 <ErrorBoundary fallback=error_boundary_fallback>{comment_list}</ErrorBoundary>
             </Transition>
+            {load_more_link}
         </div>
     }
 }
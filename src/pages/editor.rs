@@ -4,8 +4,10 @@
 use crate::models::article::Article;
 
 use crate::{
-    app::ArticleSlugParam, error_template::error_boundary_fallback,
-    models::article::ArticleEditFields,
+    app::{ArticleSlugParam, NBSP},
+    error_template::error_boundary_fallback,
+    models::article::{ArticleEditFields, EditRejection},
+    pages::mention::MentionSuggestions,
 };
 use leptos::*;
 use leptos_router::*;
@@ -21,15 +23,17 @@ async fn get_article_for_editing(slug: String) -> Result<ArticleEditFields, Serv
 
 // NOTE: the macro does some magic that doesn't understand plain typedef name,
 // so the outer `Result` is not inlined here.
-type CreateOrUpdateResult = Result<String, Vec<String>>;
+type CreateOrUpdateResult = Result<String, EditRejection>;
 
 #[server]
 async fn create_or_update_post(
     slug: Option<String>,
+    revision: Option<i64>,
     title: String,
     about: String,
     body: String,
     tags: String,
+    regenerate_slug: Option<String>,
 ) -> Result<CreateOrUpdateResult, ServerFnError> {
     let author = crate::auth::require_login()?;
     let tags = tags.to_lowercase();
@@ -37,19 +41,30 @@ async fn create_or_update_post(
 
     let res;
     if let Some(slug) = slug {
-        res = Article::update(&author, &slug, &title, &about, &body, &tags)
-            .await
-            .map(|res| match res {
-                Some(errors) => Err(errors),
-                None => Ok(slug),
-            })
-            .map_err(|e| {
-                tracing::error!("article update failed: {:?}", e);
-                ServerFnError::ServerError("article update failed".into())
-            });
+        // Without the revision the edit couldn't be checked for conflicts
+        let Some(revision) = revision else {
+            return Err(ServerFnError::ServerError("missing revision".into()));
+        };
+        let regenerate_slug = regenerate_slug.is_some();
+        res = Article::update(
+            &author,
+            &slug,
+            revision,
+            &title,
+            &about,
+            &body,
+            &tags,
+            regenerate_slug,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("article update failed: {:?}", e);
+            ServerFnError::ServerError("article update failed".into())
+        });
     } else {
         res = Article::create(&author, &title, &about, &body, &tags)
             .await
+            .map(|res| res.map_err(EditRejection::Invalid))
             .map_err(|e| {
                 tracing::error!("article creation failed: {:?}", e);
                 ServerFnError::ServerError("article creation failed".into())
@@ -61,16 +76,48 @@ async fn create_or_update_post(
     res
 }
 
+type EditorAction = Action<CreateOrUpdatePost, Result<CreateOrUpdateResult, ServerFnError>>;
+
+fn validation_errors(action: EditorAction) -> impl Fn() -> Vec<String> + Copy {
+    let result = action.value();
+    move || {
+        if let Some(Ok(Err(EditRejection::Invalid(errors)))) = result() {
+            errors
+        } else {
+            Vec::new()
+        }
+    }
+}
+
 #[component]
 fn EditorForm(
     #[prop(into)] button_label: String,
     #[prop(optional)] slug: Signal<String>,
+    #[prop(optional, into)] revision: MaybeSignal<Option<i64>>,
     #[prop(optional)] fields: Option<ArticleEditFields>,
-    action: Action<CreateOrUpdatePost, Result<CreateOrUpdateResult, ServerFnError>>,
+    action: EditorAction,
 ) -> impl IntoView {
+    let body_ref: NodeRef<html::Textarea> = create_node_ref();
+    // Only existing articles have a slug to regenerate
+    let rename_option = fields.is_some().then(|| {
+        view! {
+            <fieldset class="form-group">
+                <label>
+                    <input type="checkbox" name="regenerate_slug"/>
+                    {NBSP}
+                    "Update the article URL to match a changed title (old links are redirected)"
+                </label>
+            </fieldset>
+        }
+    });
     view! {
         <ActionForm action=action>
             <input type="hidden" name="slug" value=slug/>
+            <input
+                type="hidden"
+                name="revision"
+                value=move || revision().map(|r| r.to_string())
+            />
             <fieldset>
                 <fieldset class="form-group">
                     <input
@@ -92,6 +139,7 @@ fn EditorForm(
                 </fieldset>
                 <fieldset class="form-group">
                     <textarea
+                        node_ref=body_ref
                         class="form-control"
                         rows="8"
                         placeholder="Write your article (in markdown)"
@@ -99,6 +147,7 @@ fn EditorForm(
                     >
                         {fields.as_ref().map(|a| a.body.clone()).unwrap_or_default()}
                     </textarea>
+                    <MentionSuggestions textarea=body_ref/>
                 </fieldset>
                 <fieldset class="form-group">
                     <input
@@ -109,6 +158,7 @@ fn EditorForm(
                         value=fields.as_ref().map(|a| a.tags.join(" "))
                     />
                 </fieldset>
+                {rename_option}
                 <button
                     disabled=action.pending()
                     class="btn btn-lg pull-xs-right btn-primary"
@@ -121,6 +171,30 @@ fn EditorForm(
     }
 }
 
+#[component]
+fn ConflictNotice(current: ArticleEditFields) -> impl IntoView {
+    view! {
+        <div class="conflict">
+            <p>
+                "The article was changed while you were editing it. "
+                "Your version is kept in the form, and saving it again replaces the one below."
+            </p>
+            <dl>
+                <dt>Title</dt>
+                <dd>{current.title}</dd>
+                <dt>Description</dt>
+                <dd>{current.description}</dd>
+                <dt>Body</dt>
+                <dd>
+                    <pre>{current.body}</pre>
+                </dd>
+                <dt>Tags</dt>
+                <dd>{current.tags.join(" ")}</dd>
+            </dl>
+        </div>
+    }
+}
+
 #[component]
 pub fn Edit() -> impl IntoView {
     let params = use_params::<ArticleSlugParam>();
@@ -128,17 +202,26 @@ pub fn Edit() -> impl IntoView {
     let post = create_server_action::<CreateOrUpdatePost>();
     let to_edit = create_blocking_resource(slug, get_article_for_editing);
     let result = post.value();
-    let errors = move || {
-        if let Some(Ok(Err(errors))) = result() {
-            errors
+    let errors = validation_errors(post);
+    let conflict = move || {
+        if let Some(Ok(Err(EditRejection::Conflict(current)))) = result() {
+            Some(current)
         } else {
-            Vec::new()
+            None
         }
     };
+    // After a conflict, the next save is made against the version shown to the user
+    let revision = Signal::derive(move || {
+        conflict()
+            .map(|c| c.revision)
+            .or_else(|| to_edit().and_then(Result::ok).map(|a| a.revision))
+    });
     let edit_form = move || {
         to_edit().map(|a| {
             a.map(|a| {
-                view! { <EditorForm button_label="Save" slug=slug fields=a action=post/> }
+                view! {
+                    <EditorForm button_label="Save" slug=slug revision=revision fields=a action=post/>
+                }
             })
         })
     };
@@ -152,6 +235,7 @@ pub fn Edit() -> impl IntoView {
                                 <li>{error}</li>
                             </For>
                         </ul>
+                        {move || conflict().map(|current| view! { <ConflictNotice current/> })}
 
                         <Suspense fallback=|| "Loading...">
                             <ErrorBoundary fallback=error_boundary_fallback>
@@ -168,14 +252,7 @@ pub fn Edit() -> impl IntoView {
 #[component]
 pub fn New() -> impl IntoView {
     let post = create_server_action::<CreateOrUpdatePost>();
-    let result = post.value();
-    let errors = move || {
-        if let Some(Ok(Err(errors))) = result() {
-            errors
-        } else {
-            Vec::new()
-        }
-    };
+    let errors = validation_errors(post);
     view! {
         <div class="editor-page">
             <div class="container page">
@@ -1,7 +1,9 @@
-use std::num::NonZeroU8;
+use std::{collections::HashMap, num::NonZeroU8};
 
 use crate::{
-    error_template::error_boundary_fallback, models::article::Feed, pages::article::ArticlePreview,
+    error_template::error_boundary_fallback,
+    models::article::{Article, Feed, FeedFilter, FeedReason, FeedSort, TagMatch},
+    pages::article::ArticlePreview,
 };
 use leptos::*;
 use leptos_router::*;
@@ -10,45 +12,215 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub enum FeedKind {
     Feed,
+    /// Private reading list of the user
+    Bookmarks,
     Global,
     By(String),
     Favorited(String),
     Tag(String),
+    Search(String),
 }
 
+/// Most articles on a page, so that one request can't load all their details
+const MAX_PAGE_SIZE: u8 = 50;
+
+/// Splits a comma separated query parameter
+fn query_list(query: &ParamsMap, key: &str) -> Vec<String> {
+    query
+        .get(key)
+        .map(|list| {
+            list.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Date of a query parameter, if it is a valid date (YYYY-MM-DD)
+fn query_date(query: &ParamsMap, key: &str) -> Option<String> {
+    query
+        .get(key)
+        .filter(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok())
+        .cloned()
+}
+
+/// Additional filters for feeds from the query parameters:
+/// - `tag`, `exclude` and `author` take comma separated lists
+/// - `match=all` to require all of the tags, instead of any
+/// - `following` and `favorited_by` take a username, where `following` is
+///   only allowed for the logged in user
+/// - `since` and `until` take dates (YYYY-MM-DD), and `days` a number of days,
+///   where invalid values are ignored
+fn filter_from_query(query: &ParamsMap) -> FeedFilter {
+    FeedFilter {
+        tags: query_list(query, "tag"),
+        tag_match: match query.get("match").map(String::as_str) {
+            Some("all") => TagMatch::All,
+            _ => TagMatch::Any,
+        },
+        excluded_tags: query_list(query, "exclude"),
+        authors: query_list(query, "author"),
+        followed_by: query.get("following").cloned(),
+        favorited_by: query.get("favorited_by").cloned(),
+        since: query_date(query, "since"),
+        until: query_date(query, "until"),
+        within_days: query.get("days").and_then(|d| d.parse().ok()),
+        ..Default::default()
+    }
+}
+
+/// Feed of article previews, with the kind's own links as children, and
+/// an optional header below them.
+///
+/// Paged with newer/older links, or with `infinite` more articles are loaded
+/// when scrolled to the bottom. Without JS the latter falls back to links.
 #[component]
-pub fn Feed(#[prop(into)] kind: MaybeSignal<FeedKind>, children: Children) -> impl IntoView {
+pub fn Feed(
+    #[prop(into)] kind: MaybeSignal<FeedKind>,
+    #[prop(optional)] infinite: bool,
+    #[prop(optional)] header: Option<View>,
+    children: Children,
+) -> impl IntoView {
     let query = use_query_map();
     let pagination = create_memo(move |_| {
         query.with(|m| {
-            let offset = m
-                .get("offset")
-                .and_then(|s| s.parse().ok())
-                .unwrap_or_default();
             let limit = m
                 .get("limit")
-                .and_then(|s| s.parse().ok())
-                .and_then(NonZeroU8::new)
+                .and_then(|s| s.parse::<u8>().ok())
+                .and_then(|limit| NonZeroU8::new(limit.min(MAX_PAGE_SIZE)))
                 .unwrap_or_else(|| NonZeroU8::new(10).unwrap());
-            Page { offset, limit }
+            Page {
+                after: m.get("after").cloned(),
+                before: m.get("before").cloned(),
+                limit,
+                sort: m
+                    .get("sort")
+                    .map(|s| FeedSort::from_param(s))
+                    .unwrap_or_default(),
+            }
         })
     });
+    let filter = create_memo(move |_| query.with(filter_from_query));
     let feed = create_blocking_resource(
-        move || (kind(), pagination()),
-        |(kind, page)| get_feed(kind, page),
+        move || (kind(), pagination(), filter()),
+        |(kind, page, filter)| get_feed(kind, page, filter),
     );
+
+    // Pages loaded after the first one, in infinite mode
+    let more = create_rw_signal(Vec::<Article>::new());
+    let more_snippets = create_rw_signal(HashMap::<String, String>::new());
+    let more_reasons = create_rw_signal(HashMap::<String, FeedReason>::new());
+    let next = create_rw_signal(None::<String>);
+    create_effect(move |_| {
+        let first_next = feed().and_then(Result::ok).and_then(|f| f.next);
+        more.set(Vec::new());
+        more_snippets.set(HashMap::new());
+        more_reasons.set(HashMap::new());
+        next.set(first_next);
+    });
+    let load_more = create_action(move |after: &String| {
+        let page = Page {
+            after: Some(after.clone()),
+            before: None,
+            ..pagination.get_untracked()
+        };
+        get_feed(kind.get_untracked(), page, filter.get_untracked())
+    });
+    create_effect(move |_| {
+        if let Some(Ok(page)) = load_more.value()() {
+            more.update(|m| m.extend(page.articles));
+            more_snippets.update(|s| s.extend(page.snippets));
+            more_reasons.update(|r| r.extend(page.reasons));
+            next.set(page.next);
+        }
+    });
+    let fetch_next = move || {
+        if let Some(after) = next.get_untracked() {
+            if !load_more.pending().get_untracked() {
+                load_more.dispatch(after);
+            }
+        }
+    };
+    if infinite {
+        let handle = window_event_listener(ev::scroll, move |_| {
+            if scrolled_near_bottom() {
+                fetch_next();
+            }
+        });
+        on_cleanup(move || handle.remove());
+    }
+
+    let sort_link = move |option: FeedSort, text: &'static str| {
+        // The pages of one order don't continue in another
+        let href = move || {
+            query.with(|q| match option {
+                FeedSort::Newest => query_with(q, &["after", "before", "sort"], &[]),
+                _ => query_with(q, &["after", "before"], &[("sort", option.param().into())]),
+            })
+        };
+        view! {
+            <li class="nav-item">
+                <a
+                    class="nav-link"
+                    class:active=move || pagination.with(|p| p.sort == option)
+                    href=href
+                >
+                    {text}
+                </a>
+            </li>
+        }
+    };
+    // Search results are ordered by relevance
+    let sorts = move || {
+        (!matches!(kind(), FeedKind::Search(_))).then(|| {
+            view! {
+                <ul class="nav nav-pills outline-active feed-sort">
+                    {sort_link(FeedSort::Newest, "Newest")}
+                    {sort_link(FeedSort::MostReacted, "Most reacted")}
+                </ul>
+            }
+        })
+    };
+
     let previews = move || {
         feed().map(|data| {
-            data.map(|Feed { articles, count }| {
+            data.map(|Feed { articles, count, snippets, reasons, prev, next: first_next }| {
+                let all = move || {
+                    let mut all = articles.clone();
+                    all.extend(more());
+                    all
+                };
+                let pager = if infinite {
+                    view! { <LoadMore next=next on_load=fetch_next/> }.into_view()
+                } else {
+                    view! { <Pagination prev next=first_next/> }.into_view()
+                };
                 view! {
                     <For
-                        each=move || articles.clone()
+                        each=all
                         key=|article| article.slug.clone()
-                        let:article
-                    >
-                        <ArticlePreview article=create_rw_signal(article)/>
-                    </For>
-                    <Pagination page=pagination count=count/>
+                        children=move |article| {
+                            let snippet = snippets
+                                .get(&article.slug)
+                                .cloned()
+                                .or_else(|| more_snippets.with(|s| s.get(&article.slug).cloned()));
+                            let reason = reasons
+                                .get(&article.slug)
+                                .cloned()
+                                .or_else(|| more_reasons.with(|r| r.get(&article.slug).cloned()));
+                            view! {
+                                <ArticlePreview
+                                    article=create_rw_signal(article)
+                                    snippet=snippet
+                                    reason=reason
+                                />
+                            }
+                        }
+                    />
+                    <p class="feed-count">{count} " articles"</p>
+                    {pager}
                 }
             })
         })
@@ -59,6 +231,8 @@ pub fn Feed(#[prop(into)] kind: MaybeSignal<FeedKind>, children: Children) -> im
             <div class="feed-toggle">
                 <ul class="nav nav-pills outline-active">{children()}</ul>
             </div>
+            {header}
+            {sorts}
 
             <Suspense fallback=|| "Loading feed...">
                 <ErrorBoundary fallback=error_boundary_fallback>{previews}</ErrorBoundary>
@@ -67,61 +241,170 @@ pub fn Feed(#[prop(into)] kind: MaybeSignal<FeedKind>, children: Children) -> im
     }
 }
 
+fn scrolled_near_bottom() -> bool {
+    let window = window();
+    let viewport = window
+        .inner_height()
+        .ok()
+        .and_then(|h| h.as_f64())
+        .unwrap_or_default();
+    let bottom = window.scroll_y().unwrap_or_default() + viewport;
+    let height = document()
+        .document_element()
+        .map(|e| e.scroll_height())
+        .unwrap_or_default();
+    bottom >= f64::from(height) - 300.0
+}
+
+/// Percent-encodes a query string key or value, or a path segment
+pub(crate) fn encode_query_component(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// Query string with the current parameters, with some removed or replaced
+fn query_with(current: &ParamsMap, remove: &[&str], replace: &[(&str, String)]) -> String {
+    let mut params: Vec<_> = current
+        .0
+        .iter()
+        .filter(|(k, _)| !remove.contains(&k.as_str()) && !replace.iter().any(|(r, _)| r == k))
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    params.extend(replace.iter().map(|(k, v)| (*k, v.as_str())));
+    let query: Vec<_> = params
+        .into_iter()
+        .map(|(k, v)| {
+            format!(
+                "{}={}",
+                encode_query_component(k),
+                encode_query_component(v)
+            )
+        })
+        .collect();
+    format!("?{}", query.join("&"))
+}
+
 #[component]
-fn Pagination(#[prop(into)] page: Signal<Page>, count: u32) -> impl IntoView {
-    let page_links = move || {
-        let Page { offset, limit } = page();
-        let limit = u8::from(limit) as u32;
-        (0..count)
-            .step_by(limit as usize)
-            .enumerate()
-            .map(|(page, start)| {
-                let class = if start <= offset && offset < start + limit {
-                    "page-item active"
-                } else {
-                    "page-item"
-                };
-                view! {
-                    <li class=class>
-                        <A class="page-link" href=format!("?offset={}&limit={}", start, limit)>
-                            {page + 1}
-                        </A>
-                    </li>
-                }
-            })
-            .collect_view()
+fn Pagination(prev: Option<String>, next: Option<String>) -> impl IntoView {
+    let query = use_query_map();
+    let link =
+        move |param, other, cursor| query.with(|q| query_with(q, &[other], &[(param, cursor)]));
+    view! {
+        <ul class="pagination">
+            {prev
+                .map(|cursor| {
+                    view! {
+                        <li class="page-item">
+                            <A class="page-link" href=link("before", "after", cursor)>
+                                "Newer"
+                            </A>
+                        </li>
+                    }
+                })}
+            {next
+                .map(|cursor| {
+                    view! {
+                        <li class="page-item">
+                            <A class="page-link" href=link("after", "before", cursor)>
+                                "Older"
+                            </A>
+                        </li>
+                    }
+                })}
+        </ul>
+    }
+}
+
+/// Link to the next page, which loads it in place when JS is available
+#[component]
+fn LoadMore(next: RwSignal<Option<String>>, on_load: impl Fn() + 'static) -> impl IntoView {
+    let query = use_query_map();
+    let href = move || {
+        next().map(|cursor| query.with(|q| query_with(q, &["before"], &[("after", cursor)])))
     };
-    view! { <ul class="pagination">{page_links}</ul> }
+    view! {
+        <Show when=move || next.with(Option::is_some)>
+            <a
+                class="btn btn-sm btn-outline-primary"
+                href=href
+                on:click=move |ev| {
+                    ev.prevent_default();
+                    on_load();
+                }
+            >
+                "Load more"
+            </a>
+        </Show>
+    }
 }
 
-#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
 struct Page {
-    offset: u32,
+    after: Option<String>,
+    before: Option<String>,
     limit: NonZeroU8,
+    /// `sort=reacted` to order by the reactions, instead of the newest first
+    sort: FeedSort,
+}
+
+/// Articles of the feed, shared by the pages and the other formats of feeds.
+///
+/// The personal feed and reading list are of `options.user`, and empty without one.
+#[cfg(feature = "ssr")]
+pub async fn load(
+    kind: &FeedKind,
+    options: &crate::models::article::FeedOptions,
+) -> Result<Feed, sqlx::Error> {
+    match kind {
+        FeedKind::Feed => match &options.user {
+            Some(user) => Feed::feed(user, options).await,
+            None => Ok(Feed::default()),
+        },
+        FeedKind::Bookmarks => match &options.user {
+            Some(user) => Feed::bookmarks(user, options).await,
+            None => Ok(Feed::default()),
+        },
+        FeedKind::Global => Feed::global(options).await,
+        FeedKind::By(user) => Feed::by(user, options).await,
+        FeedKind::Favorited(user) => Feed::favorited(user, options).await,
+        FeedKind::Tag(tag) => Feed::tag(tag, options).await,
+        FeedKind::Search(query) => Feed::search(query, options).await,
+    }
 }
 
 #[server]
-async fn get_feed(kind: FeedKind, page: Page) -> Result<Feed, ServerFnError> {
-    use crate::models::article::FeedOptions;
+async fn get_feed(kind: FeedKind, page: Page, filter: FeedFilter) -> Result<Feed, ServerFnError> {
+    use crate::models::article::{FeedCursor, FeedOptions};
 
+    let cursor = match (page.after, page.before) {
+        (Some(after), _) => Some(FeedCursor::After(after)),
+        (None, Some(before)) => Some(FeedCursor::Before(before)),
+        (None, None) => None,
+    };
     let options = FeedOptions {
         user: crate::auth::authenticated_username(),
-        offset: page.offset,
-        limit: page.limit.into(),
+        cursor,
+        limit: page.limit.get().min(MAX_PAGE_SIZE),
+        sort: page.sort,
+        filter,
     };
-    match kind {
-        FeedKind::Feed => {
-            let Some(user) = &options.user else {
-                return Err(ServerFnError::ServerError("Not logged in".into()));
-            };
-            Feed::feed(user, &options).await
-        }
-        FeedKind::Global => Feed::global(&options).await,
-        FeedKind::By(user) => Feed::by(&user, &options).await,
-        FeedKind::Favorited(user) => Feed::favorited(&user, &options).await,
-        FeedKind::Tag(tag) => Feed::tag(&tag, &options).await,
+    if matches!(kind, FeedKind::Feed | FeedKind::Bookmarks) && options.user.is_none() {
+        return Err(ServerFnError::ServerError("Not logged in".into()));
+    }
+    if options.filter.followed_by.is_some() && options.filter.followed_by != options.user {
+        return Err(ServerFnError::ServerError(
+            "Only your own follows can be filtered by".into(),
+        ));
     }
-    .map_err(|e| {
+    load(&kind, &options).await.map_err(|e| {
         tracing::error!("sql error when fetching feed: {:?}", e);
         ServerFnError::ServerError("Could not fetch feed".into())
     })
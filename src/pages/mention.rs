@@ -0,0 +1,140 @@
+//! Completing `@username` mentions while writing
+
+use leptos::*;
+
+/// Number of suggestions shown at once
+const SUGGESTIONS: i64 = 5;
+
+#[server]
+async fn suggest_usernames(prefix: String) -> Result<Vec<String>, ServerFnError> {
+    crate::auth::require_login()?;
+    crate::models::mention::suggest(&prefix, SUGGESTIONS)
+        .await
+        .map_err(|e| {
+            tracing::error!("could not suggest usernames: {:?}", e);
+            ServerFnError::ServerError("Could not suggest usernames".into())
+        })
+}
+
+/// Mention being written before the caret: byte positions of the `@` and
+/// the caret, and the name so far
+#[derive(Clone, PartialEq)]
+struct Partial {
+    at: usize,
+    caret: usize,
+    prefix: String,
+}
+
+fn is_username_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Byte position of the UTF-16 position, which is what the DOM gives
+fn byte_position(text: &str, utf16: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= utf16 {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+fn partial_mention(text: &str, caret: usize) -> Option<Partial> {
+    let before = &text[..caret];
+    let start = before
+        .rfind(|c| !is_username_char(c))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let at = start.checked_sub(1)?;
+    if !before[at..].starts_with('@') || start == caret {
+        return None;
+    }
+    // Like in email addresses
+    let after_word = before[..at]
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_');
+    (!after_word).then(|| Partial {
+        at,
+        caret,
+        prefix: before[start..].to_owned(),
+    })
+}
+
+/// Suggests usernames to complete a mention in the textarea with, shown
+/// under it while typing `@name`
+#[component]
+pub fn MentionSuggestions(textarea: NodeRef<html::Textarea>) -> impl IntoView {
+    let partial = create_rw_signal(None::<Partial>);
+
+    let update = move || {
+        let Some(el) = textarea.get_untracked() else {
+            return;
+        };
+        let text = el.value();
+        let found = match el.selection_start() {
+            Ok(Some(caret)) => partial_mention(&text, byte_position(&text, caret as usize)),
+            _ => None,
+        };
+        partial.set(found);
+    };
+    textarea.on_load(move |el| {
+        let _ = el
+            .on(ev::input, move |_| update())
+            .on(ev::keydown, move |ev| {
+                if ev.key() == "Escape" {
+                    partial.set(None);
+                }
+            });
+    });
+
+    let suggestions = create_local_resource(
+        move || partial.with(|p| p.as_ref().map(|p| p.prefix.clone())),
+        |prefix| async move {
+            match prefix {
+                Some(prefix) => suggest_usernames(prefix).await.unwrap_or_default(),
+                None => Vec::new(),
+            }
+        },
+    );
+
+    let complete = move |username: String| {
+        let (Some(el), Some(p)) = (textarea.get_untracked(), partial.get_untracked()) else {
+            return;
+        };
+        let text = el.value();
+        let mention = format!("@{} ", username);
+        el.set_value(&format!("{}{}{}", &text[..p.at], mention, &text[p.caret..]));
+        let caret = text[..p.at].encode_utf16().count() + mention.len();
+        let _ = el.set_selection_range(caret as u32, caret as u32);
+        let _ = el.focus();
+        partial.set(None);
+    };
+
+    move || {
+        let names = suggestions.get().unwrap_or_default();
+        (partial.with(Option::is_some) && !names.is_empty()).then(|| {
+            view! {
+                <div class="list-group mention-suggestions">
+                    {names
+                        .into_iter()
+                        .map(|name| {
+                            let label = format!("@{}", name);
+                            view! {
+                                <button
+                                    type="button"
+                                    class="list-group-item list-group-item-action"
+                                    on:click=move |_| complete(name.clone())
+                                >
+                                    {label}
+                                </button>
+                            }
+                        })
+                        .collect_view()}
+                </div>
+            }
+        })
+    }
+}
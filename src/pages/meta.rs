@@ -0,0 +1,181 @@
+//! Metadata of pages for link previews and search engines.
+//!
+//! The pages load their data with blocking resources, so the tags are in the
+//! server-rendered head for crawlers that don't run JS.
+
+use leptos::*;
+use leptos_meta::{Link, Meta, Title};
+use serde_json::json;
+
+use crate::{
+    models::{article::Article, user::Profile},
+    pages::profile::profile_link,
+};
+
+/// Length of descriptions, as most previews cut them about there
+const DESCRIPTION_LEN: usize = 200;
+
+fn truncated(text: &str) -> String {
+    match text.char_indices().nth(DESCRIPTION_LEN) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_owned(),
+    }
+}
+
+/// ISO 8601 form of the timestamps from the database, which are in UTC
+fn iso_date(date: &str) -> String {
+    format!("{}Z", date.replacen(' ', "T", 1))
+}
+
+/// Only absolute image URLs are displayed, see `ProfileImg`
+fn image_url(image: &Option<String>) -> Option<String> {
+    image.clone().filter(|url| url.starts_with("https://"))
+}
+
+/// JSON for a script element, which ends at the first `</script`
+fn script_json(value: &serde_json::Value) -> String {
+    value
+        .to_string()
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+}
+
+/// Title, description, canonical link, OpenGraph and Twitter Card tags, and
+/// schema.org JSON-LD of a page
+#[component]
+fn PageMeta(
+    title: String,
+    description: String,
+    /// Absolute URL of the page
+    url: String,
+    /// OpenGraph type, e.g. "article"
+    og_type: &'static str,
+    image: Option<String>,
+    json_ld: serde_json::Value,
+    /// Additional OpenGraph properties
+    #[prop(optional)]
+    properties: Vec<(&'static str, String)>,
+) -> impl IntoView {
+    let card = if image.is_some() {
+        "summary_large_image"
+    } else {
+        "summary"
+    };
+    view! {
+        <Title text=format!("{} — Conduit", title)/>
+        <Meta name="description" content=description.clone()/>
+        <Link rel="canonical" href=url.clone()/>
+        <Meta property="og:site_name" content="Conduit"/>
+        <Meta property="og:type" content=og_type/>
+        <Meta property="og:title" content=title.clone()/>
+        <Meta property="og:description" content=description.clone()/>
+        <Meta property="og:url" content=url/>
+        {image
+            .clone()
+            .map(|image| {
+                view! {
+                    <Meta property="og:image" content=image.clone()/>
+                    <Meta name="twitter:image" content=image/>
+                }
+            })}
+        {properties
+            .into_iter()
+            .map(|(property, content)| view! { <Meta property=property content=content/> })
+            .collect_view()}
+        <Meta name="twitter:card" content=card/>
+        <Meta name="twitter:title" content=title/>
+        <Meta name="twitter:description" content=description/>
+        <script type="application/ld+json" inner_html=script_json(&json_ld)></script>
+    }
+}
+
+#[component]
+pub fn ArticlePageMeta(article: Article) -> impl IntoView {
+    let base = crate::site::base_url();
+    let url = format!("{}/article/{}", base, article.slug);
+    let author_url = format!("{}{}", base, profile_link(&article.author.username));
+    let image = image_url(&article.author.image);
+    let published = iso_date(&article.created_at);
+    let modified = article.updated_at.as_deref().map(iso_date);
+    let description = truncated(&article.description);
+
+    let mut properties = vec![
+        ("article:published_time", published.clone()),
+        ("article:author", author_url.clone()),
+    ];
+    properties.extend(modified.clone().map(|date| ("article:modified_time", date)));
+    properties.extend(article.tags.iter().map(|tag| ("article:tag", tag.clone())));
+
+    let json_ld = json!({
+        "@context": "https://schema.org",
+        "@type": "Article",
+        "headline": article.title,
+        "description": description,
+        "url": url,
+        "mainEntityOfPage": url,
+        "datePublished": published,
+        "dateModified": modified.unwrap_or_else(|| published.clone()),
+        "keywords": article.tags,
+        "image": image,
+        "author": {
+            "@type": "Person",
+            "name": article.author.username,
+            "url": author_url,
+        },
+    });
+
+    view! {
+        <PageMeta
+            title=article.title
+            description=description
+            url=url
+            og_type="article"
+            image=image
+            json_ld=json_ld
+            properties=properties
+        />
+    }
+}
+
+#[component]
+pub fn ProfilePageMeta(profile: Profile) -> impl IntoView {
+    let url = format!(
+        "{}{}",
+        crate::site::base_url(),
+        profile_link(&profile.username)
+    );
+    let image = image_url(&profile.image);
+    let description = profile
+        .bio
+        .as_deref()
+        .filter(|bio| !bio.trim().is_empty())
+        .map(truncated)
+        .unwrap_or_else(|| format!("Articles by {} on Conduit", profile.username));
+
+    let json_ld = json!({
+        "@context": "https://schema.org",
+        "@type": "ProfilePage",
+        "url": url,
+        "mainEntity": {
+            "@type": "Person",
+            "name": profile.username,
+            "alternateName": profile.username,
+            "description": profile.bio,
+            "image": image,
+            "url": url,
+        },
+    });
+
+    view! {
+        <PageMeta
+            title=profile.username.clone()
+            description=description
+            url=url
+            og_type="profile"
+            image=image
+            json_ld=json_ld
+            properties=vec![("profile:username", profile.username)]
+        />
+    }
+}
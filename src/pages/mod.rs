@@ -1,5 +1,11 @@
 pub mod article;
 pub mod editor;
 pub mod feed;
+pub mod mention;
+pub mod meta;
+pub mod mute;
+pub mod notifications;
 pub mod profile;
+pub mod reaction;
+pub mod tag;
 pub mod user;
@@ -0,0 +1,144 @@
+use crate::{
+    app::NBSP,
+    models::mute::{MuteKind, MutedUser},
+    pages::profile::{profile_link, ProfileImg},
+};
+use leptos::*;
+use leptos_router::*;
+
+pub type SetMuteAction = Action<SetMute, Result<(), ServerFnError>>;
+
+#[server]
+async fn mute_status(username: String) -> Result<Option<MuteKind>, ServerFnError> {
+    let Some(user) = crate::auth::authenticated_username() else {
+        return Ok(None);
+    };
+    Ok(MutedUser::kind(&user, &username).await?)
+}
+
+#[server]
+async fn muted_users() -> Result<Vec<MutedUser>, ServerFnError> {
+    let user = crate::auth::require_login()?;
+    MutedUser::for_user(&user).await.map_err(|e| {
+        tracing::error!("failed to get muted users: {:?}", e);
+        ServerFnError::ServerError("Could not fetch muted users".into())
+    })
+}
+
+/// Mutes or blocks the user, or without a kind undoes it
+#[server]
+pub async fn set_mute(username: String, kind: Option<MuteKind>) -> Result<(), ServerFnError> {
+    let user = crate::auth::require_login()?;
+    if user == username {
+        return Err(ServerFnError::ServerError("Can't mute oneself".into()));
+    }
+    MutedUser::set(&user, &username, kind).await.map_err(|e| {
+        tracing::error!("failed to mute: {:?}", e);
+        ServerFnError::ServerError("Could not mute the user".into())
+    })
+}
+
+#[component]
+fn MuteForm(
+    action: SetMuteAction,
+    username: String,
+    kind: Option<MuteKind>,
+    label: &'static str,
+) -> impl IntoView {
+    view! {
+        <ActionForm action=action class="mute-form">
+            <input type="hidden" name="username" value=username/>
+            {kind.map(|kind| view! { <input type="hidden" name="kind" value=format!("{:?}", kind)/> })}
+            <button
+                type="submit"
+                disabled=action.pending()
+                class="btn btn-sm btn-outline-secondary action-btn"
+            >
+                {label}
+            </button>
+        </ActionForm>
+    }
+}
+
+/// Mute and block buttons on a profile
+#[component]
+pub fn MuteControls(username: String, action: SetMuteAction) -> impl IntoView {
+    let name = username.clone();
+    let status = create_resource(
+        move || action.version().get(),
+        move |_| mute_status(name.clone()),
+    );
+
+    move || {
+        let username = username.clone();
+        status().and_then(Result::ok).map(|status| {
+            let buttons = match status {
+                None => vec![(Some(MuteKind::Mute), "Mute"), (Some(MuteKind::Block), "Block")],
+                Some(MuteKind::Mute) => vec![(None, "Unmute"), (Some(MuteKind::Block), "Block")],
+                Some(MuteKind::Block) => vec![(None, "Unblock")],
+            };
+            buttons
+                .into_iter()
+                .map(|(kind, label)| {
+                    view! { <MuteForm action=action username=username.clone() kind=kind label=label/> }
+                })
+                .collect_view()
+        })
+    }
+}
+
+/// Muted and blocked users, for the settings
+#[component]
+pub fn MutedUsers() -> impl IntoView {
+    let action = create_server_action::<SetMute>();
+    let muted = create_resource(move || action.version().get(), |_| muted_users());
+
+    let list = move || {
+        muted().and_then(Result::ok).map(|muted| {
+            if muted.is_empty() {
+                return view! { <p>"You haven't muted or blocked anyone."</p> }.into_view();
+            }
+            view! {
+                <ul class="muted-users">
+                    {muted
+                        .into_iter()
+                        .map(|muted| {
+                            let (state, label) = match muted.kind {
+                                MuteKind::Mute => ("Muted", "Unmute"),
+                                MuteKind::Block => ("Blocked", "Unblock"),
+                            };
+                            view! {
+                                <li>
+                                    <A href=profile_link(&muted.username)>
+                                        <ProfileImg src=muted.image class="comment-author-img"/>
+                                        {NBSP}
+                                        {muted.username.clone()}
+                                    </A>
+                                    {NBSP}
+                                    {state}
+                                    {NBSP}
+                                    <MuteForm
+                                        action=action
+                                        username=muted.username
+                                        kind=None
+                                        label=label
+                                    />
+                                </li>
+                            }
+                        })
+                        .collect_view()}
+                </ul>
+            }
+            .into_view()
+        })
+    };
+
+    view! {
+        <h4>"Muted and blocked users"</h4>
+        <p>
+            "Articles and comments of muted users are hidden from you. "
+            "Blocked users also can't follow you, comment on your articles or mention you."
+        </p>
+        <Suspense>{list}</Suspense>
+    }
+}
@@ -0,0 +1,302 @@
+use crate::{
+    app::NBSP,
+    error_template::error_boundary_fallback,
+    models::{
+        digest::DigestFrequency,
+        notification::{Notification, NotificationKind},
+    },
+    pages::profile::profile_link,
+};
+use leptos::*;
+use leptos_router::*;
+
+pub type MarkReadAction = Action<MarkNotificationsRead, Result<(), ServerFnError>>;
+
+#[server]
+async fn unread_notifications() -> Result<i64, ServerFnError> {
+    let Some(user) = crate::auth::authenticated_username() else {
+        return Ok(0);
+    };
+    Ok(Notification::unread_count(&user).await?)
+}
+
+#[server]
+async fn notifications() -> Result<Vec<Notification>, ServerFnError> {
+    let user = crate::auth::require_login()?;
+    Notification::for_user(&user).await.map_err(|e| {
+        tracing::error!("failed to get notifications: {:?}", e);
+        ServerFnError::ServerError("Could not fetch notifications".into())
+    })
+}
+
+/// Marks the notification read, or all of them without an ID
+#[server]
+pub async fn mark_notifications_read(id: Option<i64>) -> Result<(), ServerFnError> {
+    let user = crate::auth::require_login()?;
+    Ok(Notification::mark_read(&user, id).await?)
+}
+
+#[server]
+async fn notification_settings() -> Result<Vec<(NotificationKind, bool)>, ServerFnError> {
+    let user = crate::auth::require_login()?;
+    let opted_out = Notification::opted_out(&user).await?;
+    Ok(NotificationKind::ALL
+        .into_iter()
+        .map(|kind| (kind, !opted_out.contains(&kind)))
+        .collect())
+}
+
+#[server]
+async fn set_notification_enabled(
+    kind: NotificationKind,
+    enabled: bool,
+) -> Result<(), ServerFnError> {
+    let user = crate::auth::require_login()?;
+    Ok(Notification::set_enabled(&user, kind, enabled).await?)
+}
+
+#[server]
+async fn digest_frequency() -> Result<DigestFrequency, ServerFnError> {
+    let user = crate::auth::require_login()?;
+    Ok(crate::models::digest::DigestSubscription::frequency(&user).await?)
+}
+
+#[server]
+async fn set_digest_frequency(frequency: DigestFrequency) -> Result<(), ServerFnError> {
+    let user = crate::auth::require_login()?;
+    Ok(crate::models::digest::DigestSubscription::set_frequency(&user, frequency).await?)
+}
+
+/// Action for marking notifications read, shared with the bell in the nav
+pub fn use_mark_read() -> MarkReadAction {
+    expect_context()
+}
+
+/// Link to the notifications with the unread count
+#[component]
+pub fn NotificationBell() -> impl IntoView {
+    let mark_read = use_mark_read();
+    let location = use_location();
+    // Checked again on navigation
+    let unread = create_resource(
+        move || (location.pathname.get(), mark_read.version().get()),
+        |_| unread_notifications(),
+    );
+    let count = move || {
+        unread()
+            .and_then(Result::ok)
+            .filter(|n| *n > 0)
+            .map(|n| view! { <span class="tag-pill tag-default">{n}</span> })
+    };
+    view! {
+        <li class="nav-item">
+            <A class="nav-link" active_class="active" href="/notifications" exact=true>
+                <i class="ion-ios-bell"></i>
+                {NBSP}
+                <Transition>{count}</Transition>
+            </A>
+        </li>
+    }
+}
+
+/// Text of the notification, and the link to what it is about
+pub(crate) fn describe(notification: &Notification) -> (String, String) {
+    let title = notification
+        .article_title
+        .clone()
+        .unwrap_or_else(|| "your article".into());
+    let article_link = notification
+        .article
+        .as_ref()
+        .map(|slug| format!("/article/{}", slug))
+        .unwrap_or_default();
+    let actor = &notification.actor;
+    match notification.kind {
+        NotificationKind::Follow => (format!("{} followed you", actor), profile_link(actor)),
+        NotificationKind::Favorite => (format!("{} favorited {}", actor, title), article_link),
+        NotificationKind::Comment => (
+            format!("{} commented on {}", actor, title),
+            format!("{}#comments", article_link),
+        ),
+        NotificationKind::Reply => (
+            format!("{} replied to your comment on {}", actor, title),
+            format!("{}#comments", article_link),
+        ),
+        NotificationKind::Mention => {
+            let title = notification
+                .article_title
+                .as_deref()
+                .unwrap_or("an article");
+            match notification.comment {
+                Some(_) => (
+                    format!("{} mentioned you in a comment on {}", actor, title),
+                    format!("{}#comments", article_link),
+                ),
+                None => (
+                    format!("{} mentioned you in {}", actor, title),
+                    article_link,
+                ),
+            }
+        }
+    }
+}
+
+#[component]
+pub fn Notifications() -> impl IntoView {
+    let mark_read = use_mark_read();
+    let notifications = create_resource(move || mark_read.version().get(), |_| notifications());
+
+    let list = move || {
+        notifications().map(|res| {
+            res.map(|notifications| {
+                if notifications.is_empty() {
+                    return view! { <p>"No notifications yet."</p> }.into_view();
+                }
+                notifications
+                    .into_iter()
+                    .map(|notification| {
+                        let (text, link) = describe(&notification);
+                        let read = notification.read;
+                        view! {
+                            <li class="notification" class:unread=!read>
+                                <A href=link>
+                                    {if read { text } else { format!("• {}", text) }}
+                                </A>
+                                {NBSP}
+                                <span class="date">{notification.created_at}</span>
+                                {(!read)
+                                    .then(|| {
+                                        view! {
+                                            <ActionForm action=mark_read class="form-inline">
+                                                <input
+                                                    type="hidden"
+                                                    name="id"
+                                                    value=notification.id
+                                                />
+                                                <button type="submit" class="btn btn-sm btn-link">
+                                                    "Mark read"
+                                                </button>
+                                            </ActionForm>
+                                        }
+                                    })}
+                            </li>
+                        }
+                    })
+                    .collect_view()
+            })
+        })
+    };
+
+    view! {
+        <div class="container page">
+            <div class="row">
+                <div class="col-md-8 offset-md-2">
+                    <h1>"Notifications"</h1>
+                    <ActionForm action=mark_read>
+                        <button
+                            type="submit"
+                            disabled=mark_read.pending()
+                            class="btn btn-sm btn-outline-secondary"
+                        >
+                            "Mark all read"
+                        </button>
+                    </ActionForm>
+                    <Transition fallback=|| "Loading notifications...">
+                        <ErrorBoundary fallback=error_boundary_fallback>
+                            <ul class="notifications">{list}</ul>
+                        </ErrorBoundary>
+                    </Transition>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// Turning kinds of notifications on and off, for the settings
+#[component]
+pub fn NotificationSettings() -> impl IntoView {
+    let set_enabled = create_server_action::<SetNotificationEnabled>();
+    let settings = create_resource(
+        move || set_enabled.version().get(),
+        |_| notification_settings(),
+    );
+
+    let list = move || {
+        settings().and_then(Result::ok).map(|settings| {
+            settings
+                .into_iter()
+                .map(|(kind, enabled)| {
+                    view! {
+                        <li>
+                            <ActionForm action=set_enabled class="form-inline">
+                                {kind.description()}
+                                {NBSP}
+                                <input type="hidden" name="kind" value=format!("{:?}", kind)/>
+                                <input type="hidden" name="enabled" value=(!enabled).to_string()/>
+                                <button
+                                    type="submit"
+                                    disabled=set_enabled.pending()
+                                    class="btn btn-sm btn-outline-secondary"
+                                >
+                                    {if enabled { "Turn off" } else { "Turn on" }}
+                                </button>
+                            </ActionForm>
+                        </li>
+                    }
+                })
+                .collect_view()
+        })
+    };
+
+    view! {
+        <h4>"Notifications"</h4>
+        <Suspense>
+            <ul class="notification-settings">{list}</ul>
+        </Suspense>
+    }
+}
+
+/// How often to get an email digest of new articles and notifications
+#[component]
+pub fn DigestSettings() -> impl IntoView {
+    let set_frequency = create_server_action::<SetDigestFrequency>();
+    let frequency = create_resource(
+        move || set_frequency.version().get(),
+        |_| digest_frequency(),
+    );
+
+    let options = move || {
+        frequency().and_then(Result::ok).map(|current| {
+            DigestFrequency::ALL
+                .into_iter()
+                .map(|frequency| {
+                    view! {
+                        <option value=format!("{:?}", frequency) selected=frequency == current>
+                            {frequency.description()}
+                        </option>
+                    }
+                })
+                .collect_view()
+        })
+    };
+
+    view! {
+        <h4>"Email digest"</h4>
+        <p>"New articles from the people you follow, and your unread notifications."</p>
+        <ActionForm action=set_frequency class="form-inline">
+            <Suspense>
+                <select name="frequency" class="form-control">
+                    {options}
+                </select>
+            </Suspense>
+            {NBSP}
+            <button
+                type="submit"
+                disabled=set_frequency.pending()
+                class="btn btn-sm btn-outline-secondary"
+            >
+                "Save"
+            </button>
+        </ActionForm>
+    }
+}
@@ -1,8 +1,12 @@
 use crate::{
     app::{use_current_user, FollowButton, NavLink, NBSP},
     error_template::error_boundary_fallback,
-    models::user::Profile,
-    pages::feed::{Feed, FeedKind},
+    models::user::{FollowList, Profile, ProfilePage},
+    pages::{
+        feed::{Feed, FeedKind},
+        meta::ProfilePageMeta,
+        mute::{MuteControls, SetMute},
+    },
 };
 use leptos::*;
 use leptos_router::*;
@@ -20,6 +24,14 @@ pub fn ProfileRoute() -> impl IntoView {
             // TODO: maybe add redirection logic on 404 to strip trailing /
             <Route path="/" view=|| view! { <ProfileFeed/> }/>
             <Route path="/favorites" view=|| view! { <ProfileFeed favorites=true/> }/>
+            <Route
+                path="/followers"
+                view=|| view! { <ProfileFollows list=FollowList::Followers/> }
+            />
+            <Route
+                path="/following"
+                view=|| view! { <ProfileFollows list=FollowList::Following/> }
+            />
         </Route>
     }
 }
@@ -42,19 +54,49 @@ pub fn Profile() -> impl IntoView {
     let params = use_params::<UserParam>();
     let username = move || params().expect("username in path").username;
 
-    let profile = create_blocking_resource(username, profile_data);
+    // Blocking ends the follows, so the profile is loaded again
+    let set_mute = create_server_action::<SetMute>();
+    let profile = create_blocking_resource(
+        move || (username(), set_mute.version().get()),
+        |(username, _)| profile_data(username),
+    );
 
     let profile_details = move || {
         profile().map(|p| {
             p.map(|p| {
+                let meta = view! { <ProfilePageMeta profile=p.clone()/> };
                 let p = create_rw_signal(p);
                 // This updates "too much" by setting the whole profile, but now it works.
                 let profile = (p, move |v| profile.set(Ok(v)));
                 view! {
+                    {meta}
                     <div class="col-xs-12 col-md-10 offset-md-1">
                         <ProfileImg src=p().image class="user-img"/>
-                        <h4>{move || p().username}</h4>
+                        <h4>
+                            {move || p().username}
+                            {move || {
+                                p()
+                                    .follows_you
+                                    .then(|| {
+                                        view! {
+                                            {NBSP}
+                                            <span class="tag-pill tag-default">"Follows you"</span>
+                                        }
+                                    })
+                            }}
+                        </h4>
                         <p>{move || p().bio}</p>
+                        <p class="follow-counts">
+                            <A href=move || format!("{}/followers", profile_link(&p().username))>
+                                {move || p().followers_count}
+                                {move || plural(p().followers_count, " follower", " followers")}
+                            </A>
+                            " · "
+                            <A href=move || format!("{}/following", profile_link(&p().username))>
+                                {move || p().following_count}
+                                " following"
+                            </A>
+                        </p>
                         <Show
                             when=move || {
                                 user.with(|u| {
@@ -63,7 +105,17 @@ pub fn Profile() -> impl IntoView {
                             }
 
                             fallback=move || {
-                                view! { <FollowButton class="action-btn" profile=profile/> }
+                                view! {
+                                    <FollowButton class="action-btn" profile=profile/>
+                                    {move || {
+                                        user.with(Option::is_some)
+                                            .then(|| {
+                                                view! {
+                                                    <MuteControls username=username() action=set_mute/>
+                                                }
+                                            })
+                                    }}
+                                }
                             }
                         >
 
@@ -106,12 +158,33 @@ struct UserParam {
     username: String,
 }
 
+fn plural(count: u32, one: &'static str, many: &'static str) -> &'static str {
+    if count == 1 {
+        one
+    } else {
+        many
+    }
+}
+
+/// Links to the lists of the profile
 #[component]
-fn ProfileFeed(#[prop(optional)] favorites: bool) -> impl IntoView {
+fn ProfileTabs() -> impl IntoView {
     let params = use_params::<UserParam>();
     let username = move || params().expect("username in path").username;
     let profile = move || profile_link(&username());
-    let fav = move || format!("{}/favorites", profile());
+    let tab = move |path: &'static str| Signal::derive(move || format!("{}{}", profile(), path));
+    view! {
+        <NavLink href=Signal::derive(profile)>My Articles</NavLink>
+        <NavLink href=tab("/favorites")>Favorited Articles</NavLink>
+        <NavLink href=tab("/followers")>Followers</NavLink>
+        <NavLink href=tab("/following")>Following</NavLink>
+    }
+}
+
+#[component]
+fn ProfileFeed(#[prop(optional)] favorites: bool) -> impl IntoView {
+    let params = use_params::<UserParam>();
+    let username = move || params().expect("username in path").username;
     let kind = if favorites {
         Signal::derive(move || FeedKind::Favorited(username()))
     } else {
@@ -119,12 +192,120 @@ fn ProfileFeed(#[prop(optional)] favorites: bool) -> impl IntoView {
     };
     view! {
         <Feed kind=kind>
-            <NavLink href=Signal::derive(profile)>My Articles</NavLink>
-            <NavLink href=Signal::derive(fav)>Favorited Articles</NavLink>
+            <ProfileTabs/>
         </Feed>
     }
 }
 
+/// Number of profiles on a page of followers or followed users
+const FOLLOWS_PAGE_SIZE: u8 = 20;
+
+#[server]
+async fn follows(
+    username: String,
+    list: FollowList,
+    after: Option<String>,
+) -> Result<ProfilePage, ServerFnError> {
+    let viewer = crate::auth::authenticated_username();
+    crate::models::user::User::follows(
+        &username,
+        list,
+        viewer.as_deref(),
+        after.as_deref(),
+        FOLLOWS_PAGE_SIZE,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("failed to get follows: {:?}", e);
+        ServerFnError::ServerError("Could not fetch the follows".into())
+    })
+}
+
+/// Followers of the profile, or the users it follows, with follow buttons
+/// for the viewer
+#[component]
+fn ProfileFollows(list: FollowList) -> impl IntoView {
+    let user = use_current_user();
+    let params = use_params::<UserParam>();
+    let query = use_query_map();
+    let username = move || params().expect("username in path").username;
+    let after = move || query.with(|q| q.get("after").cloned());
+    let page = create_blocking_resource(
+        move || (username(), after()),
+        move |(username, after)| follows(username, list, after),
+    );
+
+    let profiles = move || {
+        page().map(|page| {
+            page.map(|ProfilePage { profiles, next }| {
+                let empty = profiles.is_empty().then(|| match list {
+                    FollowList::Followers => "No followers yet.",
+                    FollowList::Following => "Not following anyone yet.",
+                });
+                let items = profiles
+                    .into_iter()
+                    .map(|profile| {
+                        let link = profile_link(&profile.username);
+                        let is_viewer = user
+                            .with(|u| u.as_ref().is_some_and(|u| u.username == profile.username));
+                        let follows_you = profile.follows_you.then(|| {
+                            view! { <span class="tag-pill tag-default">"Follows you"</span> }
+                        });
+                        let image = profile.image.clone();
+                        let name = profile.username.clone();
+                        let p = create_rw_signal(profile);
+                        let button = (!is_viewer).then(|| {
+                            view! {
+                                <FollowButton
+                                    class="pull-xs-right"
+                                    profile=(p, move |v| p.set(v))
+                                />
+                            }
+                        });
+                        view! {
+                            <li class="follow-item">
+                                {button}
+                                <A href=link.clone()>
+                                    <ProfileImg src=image class="comment-author-img"/>
+                                </A>
+                                {NBSP}
+                                <A href=link>{name}</A>
+                                {NBSP}
+                                {follows_you}
+                            </li>
+                        }
+                    })
+                    .collect_view();
+                let next = next.map(|cursor| {
+                    view! {
+                        <A class="btn btn-sm btn-outline-primary" href=format!("?after={}", cursor)>
+                            "Next page"
+                        </A>
+                    }
+                });
+                view! {
+                    {empty}
+                    <ul class="follow-list">{items}</ul>
+                    {next}
+                }
+            })
+        })
+    };
+
+    view! {
+        <div class="col-md-9">
+            <div class="feed-toggle">
+                <ul class="nav nav-pills outline-active">
+                    <ProfileTabs/>
+                </ul>
+            </div>
+            <Transition fallback=|| "Loading...">
+                <ErrorBoundary fallback=error_boundary_fallback>{profiles}</ErrorBoundary>
+            </Transition>
+        </div>
+    }
+}
+
 #[server]
 async fn profile_data(username: String) -> Result<Profile, ServerFnError> {
     let for_user = crate::auth::authenticated_username();
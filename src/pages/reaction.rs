@@ -0,0 +1,123 @@
+use crate::{
+    app::use_current_user,
+    models::reaction::{Reaction, Reactions},
+};
+use leptos::*;
+use leptos_router::*;
+
+/// Gives the reaction to the article, or to its comment, or takes it back.
+/// Returns the reactions after the change.
+#[server]
+async fn toggle_reaction(
+    article: String,
+    comment: Option<i64>,
+    reaction: Reaction,
+    current: bool,
+) -> Result<Reactions, ServerFnError> {
+    let logged_in = crate::auth::require_login()?;
+    let reactions: Result<_, sqlx::Error> = async {
+        match comment {
+            Some(comment) => {
+                Reactions::set_comment(&logged_in, &article, comment, reaction, !current).await?;
+                let ids = serde_json::to_string(&[comment]).expect("id as json");
+                Ok(Reactions::for_comments(&ids, Some(&logged_in))
+                    .await?
+                    .remove(&comment))
+            }
+            None => {
+                Reactions::set_article(&logged_in, &article, reaction, !current).await?;
+                let slugs = serde_json::to_string(&[&article]).expect("slug as json");
+                Ok(Reactions::for_articles(&slugs, Some(&logged_in))
+                    .await?
+                    .remove(&article))
+            }
+        }
+    }
+    .await;
+    reactions.map(Option::unwrap_or_default).map_err(|e| {
+        tracing::error!("failed to toggle reaction: {:?}", e);
+        ServerFnError::ServerError("database error".into())
+    })
+}
+
+/// Reaction buttons with their counts. Without a user only the given
+/// reactions are shown.
+#[component]
+pub fn ReactionBar(
+    #[prop(into)] article: Signal<String>,
+    #[prop(optional)] comment: Option<i64>,
+    reactions: Reactions,
+) -> impl IntoView {
+    let user = use_current_user();
+    let toggle = create_server_action::<ToggleReaction>();
+    let reactions = create_rw_signal(reactions);
+
+    create_effect(move |_| {
+        if let Some(Ok(updated)) = toggle.value()() {
+            reactions.set(updated);
+        }
+    });
+
+    let buttons = move || {
+        let logged_in = user.with(Option::is_some);
+        Reaction::ALL
+            .into_iter()
+            .filter(|r| logged_in || reactions.with(|rs| rs.count(*r) != 0))
+            .map(|reaction| {
+                let count = move || reactions.with(|rs| rs.count(reaction));
+                let reacted = move || reactions.with(|rs| rs.reacted(reaction));
+                view! {
+                    <ActionForm action=toggle>
+                        <input type="hidden" name="article" value=article/>
+                        {comment.map(|id| view! { <input type="hidden" name="comment" value=id/> })}
+                        <input type="hidden" name="reaction" value=format!("{:?}", reaction)/>
+                        <input type="hidden" name="current" value=move || reacted().to_string()/>
+                        <button
+                            type="submit"
+                            disabled=move || !logged_in || toggle.pending()()
+                            class="btn btn-sm"
+                            class:btn-primary=reacted
+                            class:btn-outline-primary=move || !reacted()
+                            title=reaction.description()
+                        >
+                            {reaction.emoji()}
+                            <Show when=move || count() != 0>
+                                " " {count}
+                            </Show>
+                        </button>
+                    </ActionForm>
+                }
+            })
+            .collect_view()
+    };
+
+    view! {
+        <div class="reactions" style="display: flex; flex-direction: row; flex-wrap: wrap; gap: 5px">
+            {buttons}
+        </div>
+    }
+}
+
+/// Counts of the given reactions, for previews
+#[component]
+pub fn ReactionCounts(#[prop(into)] reactions: Signal<Reactions>) -> impl IntoView {
+    move || {
+        reactions.with(|reactions| {
+            Reaction::ALL
+                .into_iter()
+                .filter_map(|reaction| {
+                    let count = reactions.count(reaction);
+                    (count != 0).then(|| {
+                        view! {
+                            <span class="reaction-count" title=reaction.description()>
+                                {reaction.emoji()}
+                                " "
+                                {count}
+                            </span>
+                        }
+                    })
+                })
+                .collect_view()
+        })
+    }
+}
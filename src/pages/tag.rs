@@ -0,0 +1,394 @@
+use crate::{
+    app::{use_current_user, NBSP},
+    models::tag::TagMeta,
+};
+use leptos::*;
+use leptos_router::*;
+
+type ToggleTagFollowAction = Action<ToggleTagFollow, Result<bool, ServerFnError>>;
+
+#[server]
+async fn tag_follow_status(tag: String) -> Result<bool, ServerFnError> {
+    let Some(user) = crate::auth::authenticated_username() else {
+        return Ok(false);
+    };
+    let tag = TagMeta::canonical(&tag).await?;
+    Ok(crate::models::tag::TagFollow::is_following(&user, &tag).await?)
+}
+
+#[server]
+async fn followed_tags() -> Result<Vec<String>, ServerFnError> {
+    let user = crate::auth::require_login()?;
+    crate::models::tag::TagFollow::for_user(&user)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to get followed tags: {:?}", e);
+            ServerFnError::ServerError("Could not fetch followed tags".into())
+        })
+}
+
+/// Follows the tag, or what it is an alias of, or unfollows the tag as it is
+/// followed
+#[server]
+async fn toggle_tag_follow(tag: String, current: bool) -> Result<bool, ServerFnError> {
+    let user = crate::auth::require_login()?;
+    let tag = if current {
+        tag
+    } else {
+        TagMeta::canonical(&tag).await?
+    };
+    crate::models::tag::TagFollow::set(&user, &tag, !current)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to toggle tag follow: {:?}", e);
+            ServerFnError::ServerError("database error".into())
+        })
+}
+
+#[component]
+fn TagFollowForm(action: ToggleTagFollowAction, tag: String, following: bool) -> impl IntoView {
+    let (icon, label) = if following {
+        ("ion-minus-round", "Unfollow")
+    } else {
+        ("ion-plus-round", "Follow")
+    };
+    view! {
+        <ActionForm action=action>
+            <input type="hidden" name="tag" value=tag.clone()/>
+            <input type="hidden" name="current" value=following.to_string()/>
+            <button
+                type="submit"
+                disabled=action.pending()
+                class="btn btn-sm btn-outline-secondary action-btn"
+            >
+                <i class=icon></i>
+                {NBSP}
+                {label}
+                {NBSP}
+                "#"
+                {tag}
+            </button>
+        </ActionForm>
+    }
+}
+
+/// Follow button of a tag page, for the nav of its feed
+#[component]
+pub fn TagFollowButton(#[prop(into)] tag: Signal<String>) -> impl IntoView {
+    let user = use_current_user();
+    let action = create_server_action::<ToggleTagFollow>();
+    let status = create_resource(
+        move || (tag(), action.version().get()),
+        |(tag, _)| tag_follow_status(tag),
+    );
+
+    let button = move || {
+        status().and_then(Result::ok).map(|following| {
+            view! {
+                <li class="nav-item">
+                    <TagFollowForm action=action tag=tag() following=following/>
+                </li>
+            }
+        })
+    };
+
+    view! {
+        <Show when=move || user.with(Option::is_some)>
+            <Suspense>{button}</Suspense>
+        </Show>
+    }
+}
+
+/// Tags the user follows, for the settings
+#[component]
+pub fn FollowedTags() -> impl IntoView {
+    let action = create_server_action::<ToggleTagFollow>();
+    let tags = create_resource(move || action.version().get(), |_| followed_tags());
+
+    let list = move || {
+        tags().and_then(Result::ok).map(|tags| {
+            if tags.is_empty() {
+                return view! { <p>"You don't follow any tags."</p> }.into_view();
+            }
+            view! {
+                <ul class="followed-tags">
+                    {tags
+                        .into_iter()
+                        .map(|tag| {
+                            view! {
+                                <li>
+                                    <A href=format!("/tag/{}", tag) class="tag-pill tag-default">
+                                        {tag.clone()}
+                                    </A>
+                                    {NBSP}
+                                    <TagFollowForm action=action tag=tag following=true/>
+                                </li>
+                            }
+                        })
+                        .collect_view()}
+                </ul>
+            }
+            .into_view()
+        })
+    };
+
+    view! {
+        <h4>"Followed tags"</h4>
+        <p>"Articles with the tags you follow are in your feed."</p>
+        <Suspense>{list}</Suspense>
+    }
+}
+
+#[server]
+async fn tag_meta(tag: String) -> Result<TagMeta, ServerFnError> {
+    TagMeta::get(&tag).await.map_err(|e| {
+        tracing::error!("failed to get tag meta: {:?}", e);
+        ServerFnError::ServerError("Could not fetch the tag".into())
+    })
+}
+
+#[cfg(feature = "ssr")]
+fn check_tag(tag: &str) -> Result<(), ServerFnError> {
+    if !crate::models::article::Article::valid_tag(tag) {
+        return Err(ServerFnError::ServerError(
+            "Invalid tag (must be short, lowercase a-z and in kebab-case)".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that the tag is valid, and gives the tag it is an alias of, if any
+#[cfg(feature = "ssr")]
+async fn canonical_tag(tag: &str) -> Result<String, ServerFnError> {
+    check_tag(tag)?;
+    Ok(TagMeta::canonical(tag).await?)
+}
+
+#[server]
+async fn set_tag_description(tag: String, description: String) -> Result<(), ServerFnError> {
+    crate::auth::require_moderator().await?;
+    let tag = canonical_tag(&tag).await?;
+    let description = description.trim();
+    if description.len() > 300 {
+        return Err(ServerFnError::ServerError("Too long description".into()));
+    }
+    TagMeta::set_description(&tag, description)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to set tag description: {:?}", e);
+            ServerFnError::ServerError("database error".into())
+        })
+}
+
+/// Makes the alias be replaced with the tag in all articles
+#[server]
+async fn add_tag_alias(tag: String, alias: String) -> Result<(), ServerFnError> {
+    crate::auth::require_moderator().await?;
+    let tag = canonical_tag(&tag).await?;
+    let alias = alias.trim();
+    if canonical_tag(alias).await? == tag {
+        return Err(ServerFnError::ServerError("Already the same tag".into()));
+    }
+    TagMeta::set_alias(alias, &tag).await.map_err(|e| {
+        tracing::error!("failed to add tag alias: {:?}", e);
+        ServerFnError::ServerError("database error".into())
+    })
+}
+
+#[server]
+async fn remove_tag_alias(alias: String) -> Result<(), ServerFnError> {
+    crate::auth::require_moderator().await?;
+    TagMeta::remove_alias(&alias).await.map_err(|e| {
+        tracing::error!("failed to remove tag alias: {:?}", e);
+        ServerFnError::ServerError("database error".into())
+    })
+}
+
+/// Replaces the tag with the other one in all articles, and makes it an alias
+/// of the other one. Continues to the page of the other tag.
+///
+/// The tag itself isn't resolved, as merging an alias would otherwise merge
+/// the tag it is an alias of.
+#[server]
+async fn merge_tag(tag: String, into: String) -> Result<(), ServerFnError> {
+    let moderator = crate::auth::require_moderator().await?;
+    check_tag(&tag)?;
+    let into = canonical_tag(into.trim()).await?;
+    if into == tag {
+        return Err(ServerFnError::ServerError(
+            "Can't merge a tag into itself".into(),
+        ));
+    }
+    TagMeta::merge(&tag, &into).await.map_err(|e| {
+        tracing::error!("failed to merge tag: {:?}", e);
+        ServerFnError::ServerError("database error".into())
+    })?;
+    tracing::info!("{} merged tag {} into {}", moderator, tag, into);
+    leptos_axum::redirect(&format!("/tag/{}", into));
+    Ok(())
+}
+
+/// Error of the latest submission of an action
+fn action_error<I: 'static, O: 'static>(
+    action: Action<I, Result<O, ServerFnError>>,
+) -> impl Fn() -> Option<View> {
+    move || {
+        action.value().with(|value| match value {
+            Some(Err(ServerFnError::ServerError(e))) => {
+                Some(view! { <p class="error-messages">{e.clone()}</p> }.into_view())
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Description and aliases of the tag, with their management for moderators.
+/// Aliases are redirected to the tag they are replaced with.
+#[component]
+pub fn TagInfo(#[prop(into)] tag: Signal<String>) -> impl IntoView {
+    let user = use_current_user();
+    let moderator = move || user.with(|u| u.as_ref().is_some_and(|u| u.moderator));
+    let describe = create_server_action::<SetTagDescription>();
+    let add_alias = create_server_action::<AddTagAlias>();
+    let remove_alias = create_server_action::<RemoveTagAlias>();
+    let merge = create_server_action::<MergeTag>();
+    // Blocking, so that aliases are redirected before the page is sent
+    let meta = create_blocking_resource(
+        move || {
+            (
+                tag(),
+                describe.version().get(),
+                add_alias.version().get(),
+                remove_alias.version().get(),
+            )
+        },
+        |(tag, ..)| tag_meta(tag),
+    );
+
+    let info = move || {
+        meta().and_then(Result::ok).map(|meta| {
+            if let Some(tag) = &meta.alias_of {
+                return view! { <Redirect path=format!("/tag/{}", tag)/> }.into_view();
+            }
+            let aliases = (!meta.aliases.is_empty()).then(|| {
+                view! {
+                    <p class="text-muted">
+                        "Also tagged as "
+                        {meta
+                            .aliases
+                            .iter()
+                            .map(|alias| format!("#{}", alias))
+                            .collect::<Vec<_>>()
+                            .join(", ")}
+                    </p>
+                }
+            });
+            view! {
+                {(!meta.description.is_empty()).then(|| view! { <p>{meta.description.clone()}</p> })}
+                {aliases}
+                <Show when=moderator>
+                    <TagAdmin
+                        meta=meta.clone()
+                        describe=describe
+                        add_alias=add_alias
+                        remove_alias=remove_alias
+                        merge=merge
+                    />
+                </Show>
+            }
+            .into_view()
+        })
+    };
+
+    view! {
+        <div class="tag-info">
+            <Suspense>{info}</Suspense>
+        </div>
+    }
+}
+
+#[component]
+fn TagAdmin(
+    meta: TagMeta,
+    describe: Action<SetTagDescription, Result<(), ServerFnError>>,
+    add_alias: Action<AddTagAlias, Result<(), ServerFnError>>,
+    remove_alias: Action<RemoveTagAlias, Result<(), ServerFnError>>,
+    merge: Action<MergeTag, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let TagMeta {
+        tag,
+        description,
+        aliases,
+        ..
+    } = meta;
+
+    view! {
+        <details class="tag-admin">
+            <summary class="btn btn-sm btn-outline-secondary">"Manage tag"</summary>
+            <ActionForm action=describe>
+                <input type="hidden" name="tag" value=tag.clone()/>
+                <fieldset class="form-group">
+                    <textarea
+                        class="form-control"
+                        rows="2"
+                        name="description"
+                        placeholder="Description of the tag"
+                    >
+                        {description}
+                    </textarea>
+                </fieldset>
+                <button type="submit" disabled=describe.pending() class="btn btn-sm btn-primary">
+                    "Save description"
+                </button>
+            </ActionForm>
+            {action_error(describe)}
+
+            <ul class="tag-aliases">
+                {aliases
+                    .into_iter()
+                    .map(|alias| {
+                        view! {
+                            <li>
+                                "#"
+                                {alias.clone()}
+                                {NBSP}
+                                <ActionForm action=remove_alias>
+                                    <input type="hidden" name="alias" value=alias/>
+                                    <button
+                                        type="submit"
+                                        disabled=remove_alias.pending()
+                                        class="btn btn-sm btn-outline-secondary"
+                                    >
+                                        "Remove alias"
+                                    </button>
+                                </ActionForm>
+                            </li>
+                        }
+                    })
+                    .collect_view()}
+            </ul>
+            <ActionForm action=add_alias class="form-inline">
+                <input type="hidden" name="tag" value=tag.clone()/>
+                <input class="form-control" type="text" name="alias" placeholder="Alias"/>
+                {NBSP}
+                <button type="submit" disabled=add_alias.pending() class="btn btn-sm btn-outline-primary">
+                    "Add alias"
+                </button>
+            </ActionForm>
+            {action_error(add_alias)}
+
+            <ActionForm action=merge class="form-inline">
+                <input type="hidden" name="tag" value=tag/>
+                <input class="form-control" type="text" name="into" placeholder="Other tag"/>
+                {NBSP}
+                <button type="submit" disabled=merge.pending() class="btn btn-sm btn-outline-danger">
+                    "Merge into"
+                </button>
+            </ActionForm>
+            <p class="text-muted">
+                "Merging replaces this tag in all articles and makes it an alias of the other one."
+            </p>
+            {action_error(merge)}
+        </details>
+    }
+}
@@ -1,7 +1,14 @@
 use leptos::*;
 use leptos_router::*;
 
-use crate::app::use_current_user;
+use crate::{
+    app::use_current_user,
+    pages::{
+        mute::MutedUsers,
+        notifications::{DigestSettings, NotificationSettings},
+        tag::FollowedTags,
+    },
+};
 
 #[component]
 fn ErrorList(#[prop(into)] errors: Signal<Vec<String>>) -> impl IntoView {
@@ -142,6 +149,7 @@ async fn settings(
         email,
         bio,
         image,
+        ..Default::default()
     }
     .update(password.as_deref())
     .await?;
@@ -149,6 +157,57 @@ async fn settings(
     Ok(())
 }
 
+/// Path of the private feed of the logged in user, to be opened in a feed reader
+#[server]
+async fn private_feed() -> Result<String, ServerFnError> {
+    let username = crate::auth::require_login()?;
+    let token = crate::models::user::User::feed_token(&username).await?;
+    Ok(format!("/private/{}/feed.atom", token))
+}
+
+#[server]
+async fn reset_private_feed() -> Result<(), ServerFnError> {
+    let username = crate::auth::require_login()?;
+    crate::models::user::User::reset_feed_token(&username).await?;
+    Ok(())
+}
+
+/// Private feed URL, which can be reset when it has leaked
+#[component]
+fn PrivateFeed() -> impl IntoView {
+    let reset = create_server_action::<ResetPrivateFeed>();
+    let feed = create_resource(move || reset.version()(), |_| private_feed());
+
+    view! {
+        <h4>"Private feed"</h4>
+        <p>
+            "Your feed of followed authors and tags, and your reading list, for a feed reader. "
+            "Anyone with the links can read them."
+        </p>
+        <Suspense>
+            {move || {
+                feed()
+                    .and_then(Result::ok)
+                    .map(|path| {
+                        view! {
+                            <input class="form-control" type="text" readonly value=path.clone()/>
+                            <a href=path.replace("feed.atom", "feed.rss")>"RSS"</a>
+                            " · "
+                            <a href=path.replace("feed.atom", "bookmarks/feed.atom")>
+                                "Reading list"
+                            </a>
+                        }
+                    })
+            }}
+        </Suspense>
+        <ActionForm action=reset>
+            <button type="submit" disabled=reset.pending() class="btn btn-sm btn-outline-secondary">
+                "Reset link"
+            </button>
+        </ActionForm>
+    }
+}
+
 // TODO: propagate changes to other part of app e.g. profile image
 #[component]
 pub fn Settings(logout: crate::auth::LogoutAction) -> impl IntoView {
@@ -229,6 +288,16 @@ pub fn Settings(logout: crate::auth::LogoutAction) -> impl IntoView {
                         <h1 class="text-xs-center">Your Settings</h1>
                         <Suspense>{settings_form}</Suspense>
                         <hr/>
+                        <PrivateFeed/>
+                        <hr/>
+                        <NotificationSettings/>
+                        <hr/>
+                        <DigestSettings/>
+                        <hr/>
+                        <FollowedTags/>
+                        <hr/>
+                        <MutedUsers/>
+                        <hr/>
                         <ActionForm action=logout>
                             <button type="submit" class="btn btn-outline-danger">
                                 Or click here to logout.
@@ -0,0 +1,145 @@
+//! In-process broadcasting of live updates, which are sent to the browsers
+//! as server-sent events.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    convert::Infallible,
+    sync::{Mutex, OnceLock},
+};
+
+use axum::{
+    extract::Path,
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use tokio::sync::broadcast;
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    Stream, StreamExt,
+};
+
+use crate::models::comment::CommentEvent;
+
+/// Number of recent events kept for the clients that reconnect
+const RECENT_EVENTS: usize = 1024;
+
+#[derive(Clone)]
+struct Message {
+    id: u64,
+    article: String,
+    event: CommentEvent,
+}
+
+struct Hub {
+    /// Distinguishes the event IDs from the ones of earlier processes
+    epoch: i64,
+    sender: broadcast::Sender<Message>,
+    /// Last event ID and the recent events. Locked while publishing, so
+    /// subscribers get every event either from here or from the channel.
+    recent: Mutex<(u64, VecDeque<Message>)>,
+}
+
+static HUB: OnceLock<Hub> = OnceLock::new();
+
+fn hub() -> &'static Hub {
+    HUB.get_or_init(|| Hub {
+        epoch: chrono::Utc::now().timestamp(),
+        sender: broadcast::channel(RECENT_EVENTS).0,
+        recent: Mutex::new((0, VecDeque::with_capacity(RECENT_EVENTS))),
+    })
+}
+
+impl Message {
+    /// Event for a subscriber, with the comments of the users they have muted
+    /// as placeholders
+    fn to_event(&self, hidden: &HashSet<String>) -> Event {
+        let mut event = self.event.clone();
+        if let CommentEvent::Created(comment) | CommentEvent::Updated(comment) = &mut event {
+            if hidden.contains(&comment.author.username) {
+                comment.hide();
+            }
+        }
+        Event::default()
+            .id(format!("{}-{}", hub().epoch, self.id))
+            .event("comment")
+            .data(serde_json::to_string(&event).expect("comment event serializes"))
+    }
+}
+
+/// Tells the client it has missed events, and has to load the comments again
+fn resync() -> Event {
+    Event::default().event("resync").data("")
+}
+
+/// Sends the event to the subscribers of the article's comments
+pub fn publish(article: &str, event: CommentEvent) {
+    let hub = hub();
+    let mut recent = hub.recent.lock().expect("event hub lock");
+    recent.0 += 1;
+    let message = Message {
+        id: recent.0,
+        article: article.to_owned(),
+        event,
+    };
+    if recent.1.len() == RECENT_EVENTS {
+        recent.1.pop_front();
+    }
+    recent.1.push_back(message.clone());
+    // Fails only without subscribers
+    _ = hub.sender.send(message);
+}
+
+/// Subscribes to the events of the article, with the ones after
+/// `last_event_id` if reconnecting
+fn subscribe(
+    article: &str,
+    last_event_id: Option<&str>,
+    hidden: &HashSet<String>,
+) -> (Vec<Event>, broadcast::Receiver<Message>) {
+    let hub = hub();
+    let recent = hub.recent.lock().expect("event hub lock");
+    let receiver = hub.sender.subscribe();
+
+    let Some(last_event_id) = last_event_id else {
+        return (Vec::new(), receiver);
+    };
+    let last = last_event_id
+        .split_once('-')
+        .filter(|(epoch, _)| epoch.parse() == Ok(hub.epoch))
+        .and_then(|(_, id)| id.parse::<u64>().ok());
+    let oldest = recent.1.front().map_or(recent.0 + 1, |m| m.id);
+    let backlog = match last {
+        Some(last) if last + 1 >= oldest && last <= recent.0 => recent
+            .1
+            .iter()
+            .filter(|m| m.id > last && m.article == article)
+            .map(|m| m.to_event(hidden))
+            .collect(),
+        _ => vec![resync()],
+    };
+    (backlog, receiver)
+}
+
+/// Stream of comment events of the article
+pub async fn comment_events(
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let hidden = match crate::auth::server::get_username(&headers) {
+        Some(user) => crate::models::mute::MutedUser::hidden_from(&user)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("failed to get muted users: {:?}", e);
+                HashSet::new()
+            }),
+        None => HashSet::new(),
+    };
+    let last_event_id = headers.get("last-event-id").and_then(|h| h.to_str().ok());
+    let (backlog, receiver) = subscribe(&slug, last_event_id, &hidden);
+    let live = BroadcastStream::new(receiver).filter_map(move |message| match message {
+        Ok(message) if message.article == slug => Some(message.to_event(&hidden)),
+        Ok(_) => None,
+        Err(BroadcastStreamRecvError::Lagged(_)) => Some(resync()),
+    });
+    Sse::new(tokio_stream::iter(backlog).chain(live).map(Ok)).keep_alive(KeepAlive::default())
+}
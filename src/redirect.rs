@@ -0,0 +1,46 @@
+//! Permanent redirects from previous article slugs to the current ones.
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::models::article::Article;
+
+/// Splits article paths into the part before the slug, and the slug.
+fn split_slug(path: &str) -> Option<(&str, &str)> {
+    let (prefix, slug) = path.rsplit_once('/')?;
+    let segments: Vec<_> = prefix.split('/').skip(1).collect();
+    let is_article_path = matches!(
+        segments[..],
+        ["article"] | ["editor"] | ["raw", "article", _]
+    );
+    (is_article_path && !slug.is_empty()).then_some((prefix, slug))
+}
+
+pub async fn old_slug_middleware(req: Request<Body>, next: Next) -> Response {
+    let Some((prefix, slug)) = split_slug(req.uri().path()) else {
+        return next.run(req).await;
+    };
+    match Article::renamed_slug(slug).await {
+        Ok(Some(current)) => {
+            let mut location = format!("{}/{}", prefix, current);
+            if let Some(query) = req.uri().query() {
+                location.push('?');
+                location.push_str(query);
+            }
+            Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .header(header::LOCATION, location)
+                .body(Body::empty())
+                .expect("redirection response with headers")
+        }
+        Ok(None) => next.run(req).await,
+        Err(e) => {
+            tracing::error!("failed to look up renamed slug: {:?}", e);
+            next.run(req).await
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! Details about the site itself, e.g. for absolute links.
+
+/// Base URL of a local development server, matching `site-addr` in Cargo.toml
+const DEV_BASE_URL: &str = "http://127.0.0.1:3000";
+
+/// Base URL of the site for absolute links, without a trailing slash.
+///
+/// Configured with `SITE_URL` at build time, which the feeds and the email
+/// digests require, see [`url_configured`]. It is never derived from the request headers, as the links end up in
+/// responses that caches in front of the site could share with others.
+pub fn base_url() -> String {
+    match std::option_env!("SITE_URL") {
+        Some(url) => url.trim_end_matches('/').to_owned(),
+        None => DEV_BASE_URL.to_owned(),
+    }
+}
+
+/// Whether `SITE_URL` is set, instead of falling back to the local address.
+/// Without it, the links of feeds and emails, which are read elsewhere, would
+/// point to a development server.
+pub fn url_configured() -> bool {
+    std::option_env!("SITE_URL").is_some()
+}
+
+/// Domain name of the site, e.g. for `tag:` URIs (RFC 4151)
+pub fn host() -> String {
+    let url = base_url();
+    let authority = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+    let authority = authority.split('/').next().unwrap_or_default();
+    // Without the port, and possible user info
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    host.rsplit_once(':')
+        .map_or(host, |(host, _)| host)
+        .to_ascii_lowercase()
+}
@@ -0,0 +1,171 @@
+//! Sitemaps and robots.txt for search engines.
+
+use std::fmt::Write;
+
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::{pages::feed::encode_query_component, syndication::escape};
+
+/// Limit of URLs in one sitemap, from the sitemaps protocol
+const URLS_PER_SITEMAP: i64 = 50_000;
+
+/// Paths disallowed for crawlers unless configured with `ROBOTS_DISALLOW`
+const DEFAULT_DISALLOW: &str = "/editor,/settings,/login,/register,/private/,/raw/,/unsubscribe/";
+
+/// Number of URLs in the sitemap
+async fn url_count() -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "
+        select (select count(*) from article)
+            + (select count(*) from user)
+            + (select count(distinct tag) from tag)
+        ",
+    )
+    .fetch_one(crate::db::get())
+    .await
+}
+
+/// URLs of the sitemap page (from 0): articles, then profiles, then tags,
+/// with the time of the latest change to them
+async fn urls(page: i64) -> Result<Vec<(String, Option<String>)>, sqlx::Error> {
+    let rows: Vec<(i64, String, Option<String>)> = sqlx::query_as(
+        "
+        select kind, key, strftime('%Y-%m-%dT%H:%M:%SZ', lastmod) from (
+            select 0 as kind, slug as key, coalesce(updated_at, created_at) as lastmod
+            from article
+            union all
+            select 1, username, (
+                select max(coalesce(updated_at, created_at)) from article where author = username
+            )
+            from user
+            union all
+            select 2, tag, max(coalesce(updated_at, created_at))
+            from tag join article on article.slug = tag.article
+            group by tag
+        )
+        order by kind, key
+        limit ? offset ?
+        ",
+    )
+    .bind(URLS_PER_SITEMAP)
+    .bind(page * URLS_PER_SITEMAP)
+    .fetch_all(crate::db::get())
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(kind, key, lastmod)| {
+            let prefix = match kind {
+                0 => "/article/",
+                1 => "/profile/",
+                _ => "/tag/",
+            };
+            (
+                format!("{}{}", prefix, encode_query_component(&key)),
+                lastmod,
+            )
+        })
+        .collect())
+}
+
+fn xml_response(xml: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        xml,
+    )
+        .into_response()
+}
+
+fn url_set(base: &str, urls: Vec<(String, Option<String>)>) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+"#,
+    );
+    for (path, lastmod) in urls {
+        _ = write!(
+            xml,
+            "<url><loc>{}</loc>",
+            escape(&format!("{}{}", base, path))
+        );
+        if let Some(lastmod) = lastmod {
+            _ = write!(xml, "<lastmod>{}</lastmod>", lastmod);
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+fn sitemap_error(e: sqlx::Error) -> Response {
+    tracing::error!("failed to generate sitemap: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+}
+
+/// `/sitemap.xml`, which is an index of `/sitemap/<n>.xml` pages once there
+/// are too many URLs for one sitemap
+pub async fn sitemap() -> Response {
+    let base = crate::site::base_url();
+    let count = match url_count().await {
+        Ok(count) => count,
+        Err(e) => return sitemap_error(e),
+    };
+    if count <= URLS_PER_SITEMAP {
+        return match urls(0).await {
+            Ok(urls) => xml_response(url_set(&base, urls)),
+            Err(e) => sitemap_error(e),
+        };
+    }
+
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+"#,
+    );
+    let pages = (count + URLS_PER_SITEMAP - 1) / URLS_PER_SITEMAP;
+    for page in 1..=pages {
+        _ = writeln!(
+            xml,
+            "<sitemap><loc>{}</loc></sitemap>",
+            escape(&format!("{}/sitemap/{}.xml", base, page))
+        );
+    }
+    xml.push_str("</sitemapindex>\n");
+    xml_response(xml)
+}
+
+/// `/sitemap/<n>.xml`, numbered from 1
+pub async fn sitemap_page(Path(page): Path<String>) -> Response {
+    let Some(page) = page
+        .strip_suffix(".xml")
+        .and_then(|n| n.parse::<i64>().ok())
+        .filter(|n| *n >= 1)
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match urls(page - 1).await {
+        Ok(urls) if urls.is_empty() => StatusCode::NOT_FOUND.into_response(),
+        Ok(urls) => xml_response(url_set(&crate::site::base_url(), urls)),
+        Err(e) => sitemap_error(e),
+    }
+}
+
+/// `/robots.txt`, disallowing the comma separated paths configured with
+/// `ROBOTS_DISALLOW` at build time, and pointing to the sitemap
+pub async fn robots() -> Response {
+    let disallow = std::option_env!("ROBOTS_DISALLOW").unwrap_or(DEFAULT_DISALLOW);
+    let mut robots = String::from("User-agent: *\n");
+    for path in disallow.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        _ = writeln!(robots, "Disallow: {}", path);
+    }
+    _ = writeln!(robots, "\nSitemap: {}/sitemap.xml", crate::site::base_url());
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        robots,
+    )
+        .into_response()
+}
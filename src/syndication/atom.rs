@@ -0,0 +1,66 @@
+use std::fmt::Write;
+
+use chrono::SecondsFormat;
+
+use super::{escape, Channel, Format};
+
+/// Atom, see RFC 4287
+pub struct Atom;
+
+impl Format for Atom {
+    const CONTENT_TYPE: &'static str = "application/atom+xml; charset=utf-8";
+
+    fn render(channel: &Channel) -> String {
+        let date = |d: &chrono::DateTime<chrono::Utc>| d.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let mut xml = String::new();
+        _ = write!(
+            xml,
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+<title>{title}</title>
+<id>{link}</id>
+<link rel="alternate" type="text/html" href="{link}"/>
+<link rel="self" type="application/atom+xml" href="{self_link}"/>
+<updated>{updated}</updated>
+"#,
+            title = escape(&channel.title),
+            link = escape(&channel.link),
+            self_link = escape(&channel.self_link),
+            updated = date(&channel.updated),
+        );
+        for entry in &channel.entries {
+            _ = write!(
+                xml,
+                r#"<entry>
+<title>{title}</title>
+<id>{id}</id>
+<link rel="alternate" type="text/html" href="{link}"/>
+<published>{published}</published>
+<updated>{updated}</updated>
+<author><name>{author}</name><uri>{author_link}</uri></author>
+"#,
+                title = escape(&entry.title),
+                id = escape(&entry.id),
+                link = escape(&entry.link),
+                published = date(&entry.published),
+                updated = date(&entry.updated),
+                author = escape(&entry.author),
+                author_link = escape(&entry.author_link),
+            );
+            for category in &entry.categories {
+                _ = writeln!(xml, r#"<category term="{}"/>"#, escape(category));
+            }
+            _ = write!(
+                xml,
+                r#"<summary>{}</summary>
+<content type="html">{}</content>
+</entry>
+"#,
+                escape(&entry.summary),
+                escape(&entry.content_html),
+            );
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+}
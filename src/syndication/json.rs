@@ -0,0 +1,71 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use super::{Channel, Format};
+
+/// JSON Feed 1.1, see <https://www.jsonfeed.org/version/1.1/>
+pub struct Json;
+
+#[derive(Serialize)]
+struct JsonFeed<'a> {
+    version: &'static str,
+    title: &'a str,
+    home_page_url: &'a str,
+    feed_url: &'a str,
+    items: Vec<Item<'a>>,
+}
+
+#[derive(Serialize)]
+struct Item<'a> {
+    id: &'a str,
+    url: &'a str,
+    title: &'a str,
+    summary: &'a str,
+    content_html: &'a str,
+    date_published: String,
+    date_modified: String,
+    authors: [Author<'a>; 1],
+    tags: &'a [String],
+}
+
+#[derive(Serialize)]
+struct Author<'a> {
+    name: &'a str,
+    url: &'a str,
+}
+
+fn date(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl Format for Json {
+    const CONTENT_TYPE: &'static str = "application/feed+json; charset=utf-8";
+
+    fn render(channel: &Channel) -> String {
+        let feed = JsonFeed {
+            version: "https://jsonfeed.org/version/1.1",
+            title: &channel.title,
+            home_page_url: &channel.link,
+            feed_url: &channel.self_link,
+            items: channel
+                .entries
+                .iter()
+                .map(|entry| Item {
+                    id: &entry.id,
+                    url: &entry.link,
+                    title: &entry.title,
+                    summary: &entry.summary,
+                    content_html: &entry.content_html,
+                    date_published: date(&entry.published),
+                    date_modified: date(&entry.updated),
+                    authors: [Author {
+                        name: &entry.author,
+                        url: &entry.author_link,
+                    }],
+                    tags: &entry.categories,
+                })
+                .collect(),
+        };
+        serde_json::to_string(&feed).expect("JSON feed serializes")
+    }
+}
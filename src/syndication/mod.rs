@@ -0,0 +1,431 @@
+//! Syndication feeds of the article feeds, for following them in feed readers.
+
+mod atom;
+mod json;
+mod rss;
+
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    body::Body,
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use leptos::LeptosOptions;
+
+pub use atom::Atom;
+pub use json::Json;
+pub use rss::Rss;
+
+use crate::{
+    models::{
+        article::{Article, Feed, FeedOptions},
+        mention,
+        tag::TagMeta,
+        user::User,
+    },
+    pages::{
+        feed::{self, FeedKind},
+        profile::profile_link,
+    },
+};
+
+/// Number of the latest articles in a feed
+const ENTRIES: u8 = 20;
+
+/// Feed in a form shared by the output formats
+pub struct Channel {
+    pub title: String,
+    /// Page of the feed on the site
+    pub link: String,
+    /// URL of the feed itself
+    pub self_link: String,
+    pub updated: DateTime<Utc>,
+    pub entries: Vec<Entry>,
+}
+
+pub struct Entry {
+    /// Permanent id, unlike the link which changes with the slug
+    pub id: String,
+    pub link: String,
+    pub title: String,
+    pub summary: String,
+    pub content_html: String,
+    pub author: String,
+    pub author_link: String,
+    pub categories: Vec<String>,
+    pub published: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+/// Output format of a feed
+pub trait Format: 'static {
+    const CONTENT_TYPE: &'static str;
+
+    fn render(channel: &Channel) -> String;
+}
+
+/// Format chosen by the `Accept` header of the request
+pub struct Negotiated;
+
+/// Content type and renderer of a response
+type Renderer = (&'static str, fn(&Channel) -> String);
+
+/// Picks the format of a response, fixed or negotiated
+trait Select: 'static {
+    fn select(headers: &HeaderMap) -> Renderer;
+}
+
+impl<F: Format> Select for F {
+    fn select(_: &HeaderMap) -> Renderer {
+        (F::CONTENT_TYPE, F::render)
+    }
+}
+
+impl Select for Negotiated {
+    /// The supported type with the highest quality, Atom by default
+    fn select(headers: &HeaderMap) -> Renderer {
+        let accept = headers
+            .get(header::ACCEPT)
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or_default();
+        let mut best: (f32, Renderer) = (0.0, Atom::select(headers));
+        for range in accept.split(',') {
+            let mut params = range.split(';').map(str::trim);
+            let media_type = params.next().unwrap_or_default().to_ascii_lowercase();
+            let quality = params
+                .find_map(|p| p.strip_prefix("q="))
+                .and_then(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            let renderer = match media_type.as_str() {
+                "application/feed+json" | "application/json" => Json::select(headers),
+                "application/rss+xml" => Rss::select(headers),
+                "application/atom+xml" | "application/xml" | "text/xml" => Atom::select(headers),
+                _ => continue,
+            };
+            if quality > best.0 {
+                best = (quality, renderer);
+            }
+        }
+        best.1
+    }
+}
+
+/// Escapes text for XML content and attribute values
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Parses the timestamps from the database, which are in UTC
+fn parse_date(date: &str) -> DateTime<Utc> {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")
+        .map(|date| date.and_utc())
+        .unwrap_or_default()
+}
+
+/// Title and page path of the kind of feed
+fn describe(kind: &FeedKind) -> (String, String) {
+    match kind {
+        FeedKind::Feed => ("Your Feed — Conduit".into(), "/feed".into()),
+        FeedKind::Bookmarks => ("Reading list — Conduit".into(), "/bookmarks".into()),
+        FeedKind::Global => ("Conduit".into(), "/".into()),
+        FeedKind::By(user) => (
+            format!("Articles by {} — Conduit", user),
+            profile_link(user),
+        ),
+        FeedKind::Favorited(user) => (
+            format!("Favorited by {} — Conduit", user),
+            format!("{}/favorites", profile_link(user)),
+        ),
+        FeedKind::Tag(tag) => (format!("#{} — Conduit", tag), format!("/tag/{}", tag)),
+        FeedKind::Search(query) => (format!("Search: {} — Conduit", query), "/search".into()),
+    }
+}
+
+/// `tag:` URI (RFC 4151) of the entry id of an article, from the site's
+/// domain name and the creation date of the article
+fn entry_uri(published: &DateTime<Utc>, entry_id: &str) -> String {
+    format!(
+        "tag:{},{}:article:{}",
+        crate::site::host(),
+        published.format("%Y-%m-%d"),
+        entry_id
+    )
+}
+
+/// Entry ids of the articles by their slugs
+async fn entry_ids(feed: &Feed) -> Result<HashMap<String, String>, sqlx::Error> {
+    let slugs: Vec<_> = feed.articles.iter().map(|a| a.slug.as_str()).collect();
+    let slugs = serde_json::to_string(&slugs).expect("slugs as json");
+    let rows = sqlx::query!(
+        r#"
+        select slug, entry_id as "entry_id!" from article
+        where slug in (select value from json_each(?))
+        "#,
+        slugs
+    )
+    .fetch_all(crate::db::get())
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.slug, row.entry_id))
+        .collect())
+}
+
+impl Entry {
+    fn new(base: &str, article: Article, entry_id: &str, users: &HashSet<String>) -> Self {
+        let published = parse_date(&article.created_at);
+        let updated = article
+            .updated_at
+            .as_deref()
+            .map(parse_date)
+            .unwrap_or(published);
+        Self {
+            id: entry_uri(&published, entry_id),
+            link: format!("{}/article/{}", base, article.slug),
+            title: article.title,
+            summary: article.description,
+            content_html: crate::markdown::article_html(&article.body, users),
+            author_link: format!("{}{}", base, profile_link(&article.author.username)),
+            author: article.author.username,
+            categories: article.tags,
+            published,
+            updated,
+        }
+    }
+}
+
+impl Channel {
+    /// Channel of the feed, with the entry ids of its articles by their slugs
+    /// and the users mentioned in them
+    pub fn new(
+        base: &str,
+        kind: &FeedKind,
+        feed: Feed,
+        entry_ids: &HashMap<String, String>,
+        users: &HashSet<String>,
+        self_link: String,
+    ) -> Self {
+        let (title, path) = describe(kind);
+        let entries: Vec<_> = feed
+            .articles
+            .into_iter()
+            .filter_map(|article| {
+                let entry_id = entry_ids.get(&article.slug)?;
+                Some(Entry::new(base, article, entry_id, users))
+            })
+            .collect();
+        Self {
+            title,
+            link: format!("{}{}", base, path),
+            self_link,
+            updated: entries.iter().map(|e| e.updated).max().unwrap_or_default(),
+            entries,
+        }
+    }
+}
+
+/// FNV-1a, which unlike the hashers of std is the same across Rust releases,
+/// so the ETags cached by clients stay valid
+fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Responds with the body, or with "not modified" if the client has it already
+fn conditional_response(
+    content_type: &'static str,
+    body: String,
+    updated: DateTime<Utc>,
+    headers: &HeaderMap,
+) -> Response {
+    let etag = format!("\"{:016x}\"", stable_hash(body.as_bytes()));
+    let header_str = |name| headers.get(name).and_then(|h| h.to_str().ok());
+
+    // If-None-Match takes precedence, see RFC 9110 section 13.2.2
+    let not_modified = if let Some(tags) = header_str(header::IF_NONE_MATCH) {
+        tags.split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+    } else {
+        header_str(header::IF_MODIFIED_SINCE)
+            .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+            .is_some_and(|since| updated <= since)
+    };
+
+    let response = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::VARY, "Accept")
+        .header(header::ETAG, &etag)
+        .header(
+            header::LAST_MODIFIED,
+            updated.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+        );
+    if not_modified {
+        response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+    } else {
+        response.body(Body::from(body))
+    }
+    .expect("feed response with headers")
+}
+
+async fn serve<F: Select>(
+    kind: FeedKind,
+    user: Option<String>,
+    uri: &Uri,
+    headers: &HeaderMap,
+) -> Response {
+    if !crate::site::url_configured() {
+        tracing::error!("SITE_URL is not set, feeds are not served");
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    let options = FeedOptions {
+        limit: ENTRIES,
+        user,
+        ..Default::default()
+    };
+    let loaded = async {
+        let feed = feed::load(&kind, &options).await?;
+        let entry_ids = entry_ids(&feed).await?;
+        let users = mention::resolve(feed.articles.iter().map(|a| a.body.as_str())).await?;
+        Ok::<_, sqlx::Error>((feed, entry_ids, users))
+    };
+    let (feed, entry_ids, users) = match loaded.await {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            tracing::error!("failed to load feed for syndication: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let base = crate::site::base_url();
+    let self_link = format!("{}{}", base, uri);
+    let channel = Channel::new(&base, &kind, feed, &entry_ids, &users, self_link);
+    let (content_type, render) = F::select(headers);
+    conditional_response(content_type, render(&channel), channel.updated, headers)
+}
+
+async fn global<F: Select>(uri: Uri, headers: HeaderMap) -> Response {
+    serve::<F>(FeedKind::Global, None, &uri, &headers).await
+}
+
+async fn by_author<F: Select>(
+    Path(username): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    if User::profile(&username, None).await.is_err() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    serve::<F>(FeedKind::By(username), None, &uri, &headers).await
+}
+
+async fn favorited<F: Select>(
+    Path(username): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    if User::profile(&username, None).await.is_err() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    serve::<F>(FeedKind::Favorited(username), None, &uri, &headers).await
+}
+
+/// Feed of the tag, or of the tag it is an alias of, as the aliases are
+/// replaced in the articles
+async fn tagged<F: Select>(Path(tag): Path<String>, uri: Uri, headers: HeaderMap) -> Response {
+    let tag = match TagMeta::canonical(&tag).await {
+        Ok(tag) => tag,
+        Err(e) => {
+            tracing::error!("failed to resolve tag alias for syndication: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    serve::<F>(FeedKind::Tag(tag), None, &uri, &headers).await
+}
+
+#[derive(serde::Deserialize)]
+struct SearchParams {
+    #[serde(default)]
+    q: String,
+}
+
+async fn search<F: Select>(
+    Query(SearchParams { q }): Query<SearchParams>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    serve::<F>(FeedKind::Search(q), None, &uri, &headers).await
+}
+
+/// Private feed of the kind for the owner of the token
+async fn private<F: Select>(
+    kind: FeedKind,
+    token: &str,
+    uri: &Uri,
+    headers: &HeaderMap,
+) -> Response {
+    match User::by_feed_token(token).await {
+        Ok(Some(user)) => serve::<F>(kind, Some(user), uri, headers).await,
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            tracing::error!("failed to check feed token: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// "Your Feed" of the owner of the token
+async fn personal<F: Select>(Path(token): Path<String>, uri: Uri, headers: HeaderMap) -> Response {
+    private::<F>(FeedKind::Feed, &token, &uri, &headers).await
+}
+
+/// Reading list of the owner of the token
+async fn bookmarks<F: Select>(Path(token): Path<String>, uri: Uri, headers: HeaderMap) -> Response {
+    private::<F>(FeedKind::Bookmarks, &token, &uri, &headers).await
+}
+
+/// Routes of a feed in every format: `feed.atom`, `feed.rss` and `feed.json`
+/// under the path, and `feed` for the format negotiated by the `Accept` header
+macro_rules! feed_routes {
+    ($router:expr, $path:literal, $handler:ident) => {
+        $router
+            .route(concat!($path, "/feed"), get($handler::<Negotiated>))
+            .route(concat!($path, "/feed.atom"), get($handler::<Atom>))
+            .route(concat!($path, "/feed.rss"), get($handler::<Rss>))
+            .route(concat!($path, "/feed.json"), get($handler::<Json>))
+    };
+}
+
+/// Routes of the feeds, with the global one also at the root as `/feed.atom`,
+/// `/feed.rss` and `/feed.json`, since `/feed` is the page of "Your Feed"
+pub fn routes() -> Router<LeptosOptions> {
+    let router = Router::new()
+        .route("/feed.atom", get(global::<Atom>))
+        .route("/feed.rss", get(global::<Rss>))
+        .route("/feed.json", get(global::<Json>));
+    let router = feed_routes!(router, "/global", global);
+    let router = feed_routes!(router, "/profile/:username", by_author);
+    let router = feed_routes!(router, "/profile/:username/favorites", favorited);
+    let router = feed_routes!(router, "/tag/:tag", tagged);
+    let router = feed_routes!(router, "/search", search);
+    let router = feed_routes!(router, "/private/:token", personal);
+    feed_routes!(router, "/private/:token/bookmarks", bookmarks)
+}
@@ -0,0 +1,58 @@
+use std::fmt::Write;
+
+use super::{escape, Channel, Format};
+
+/// RSS 2.0, with the full content in `content:encoded`
+pub struct Rss;
+
+impl Format for Rss {
+    const CONTENT_TYPE: &'static str = "application/rss+xml; charset=utf-8";
+
+    fn render(channel: &Channel) -> String {
+        let mut xml = String::new();
+        _ = write!(
+            xml,
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/">
+<channel>
+<title>{title}</title>
+<link>{link}</link>
+<description>{title}</description>
+<atom:link rel="self" type="application/rss+xml" href="{self_link}"/>
+<lastBuildDate>{updated}</lastBuildDate>
+"#,
+            title = escape(&channel.title),
+            link = escape(&channel.link),
+            self_link = escape(&channel.self_link),
+            updated = channel.updated.to_rfc2822(),
+        );
+        for item in &channel.entries {
+            _ = write!(
+                xml,
+                r#"<item>
+<title>{title}</title>
+<link>{link}</link>
+<guid isPermaLink="false">{id}</guid>
+<pubDate>{published}</pubDate>
+<dc:creator>{author}</dc:creator>
+"#,
+                title = escape(&item.title),
+                id = escape(&item.id),
+                link = escape(&item.link),
+                published = item.published.to_rfc2822(),
+                author = escape(&item.author),
+            );
+            for category in &item.categories {
+                _ = writeln!(xml, "<category>{}</category>", escape(category));
+            }
+            _ = write!(
+                xml,
+                "<description>{}</description>\n<content:encoded>{}</content:encoded>\n</item>\n",
+                escape(&item.summary),
+                escape(&item.content_html),
+            );
+        }
+        xml.push_str("</channel>\n</rss>\n");
+        xml
+    }
+}